@@ -0,0 +1,335 @@
+use crate::scorer::ScorerArgs;
+use crate::{decode, parse_iv, read_input, Encoding};
+use clap::{Args, ValueEnum};
+use cryptopals::cookie::{decrypt_and_parse_profile, encrypt_profile};
+use cryptopals::detection::repeated_blocks;
+use cryptopals::encoding::{hex2base64, hex_decode, hex_encode};
+use cryptopals::modes::{decrypt_aes_128_cbc, decrypt_aes_128_ecb, BLOCK_SIZE};
+use cryptopals::oracle::{break_ecb, detect_mode, encryption_oracle, random_mode, EcbOracle};
+use cryptopals::padding::{pkcs7, Padding};
+use cryptopals::profiles::ProfileRegistry;
+use cryptopals::random::seeded_rng;
+use cryptopals::repeating_xor::{
+    break_repeating_key_xor_with_options, KeySizeMethod, KeySizeOptions,
+};
+use cryptopals::scan::{scan_lines, ScanOptions};
+use cryptopals::scoring::rank_candidates;
+use cryptopals::xor::{repeating_key_xor, xor};
+use cryptopals::Result;
+use rand::Rng;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Directory holding the set1 and set2 challenge crates
+const ROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
+
+/// Base64 encoded secret appended by the oracle of challenge 12
+const CHALLENGE_12_SECRET: &str = "Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRvd24gc28gbXkgaGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvbiBzdGFuZGJ5IHdhdmluZyBqdXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUgYnkK";
+
+/// Number of messages encrypted by the oracle of challenge 11
+const CHALLENGE_11_ROUNDS: usize = 1000;
+
+#[derive(Args)]
+pub struct RunArgs {
+    /// Challenge number
+    #[arg(value_parser = clap::value_parser!(u8).range(1..=13))]
+    challenge: u8,
+    /// Input file replacing the challenge's own input
+    #[arg(long)]
+    input: Option<PathBuf>,
+    /// Key replacing the challenge's own key (hex for challenge 2)
+    #[arg(long)]
+    key: Option<String>,
+    /// Seed of the random choices of challenge 11, random when omitted
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Hex encoded IV for challenge 10
+    #[arg(long)]
+    iv: Option<String>,
+    /// Block size for challenge 9
+    #[arg(long)]
+    block_size: Option<usize>,
+    /// Scoring of the plaintexts of challenges 3, 4 and 6
+    #[command(flatten)]
+    scoring: ScorerArgs,
+    /// Number of ranked candidates listed for challenge 3
+    #[arg(long, default_value_t = 1)]
+    top: usize,
+    /// Key sizes tried in challenge 6
+    #[command(flatten)]
+    key_sizes: KeySizeArgs,
+    /// Write the plaintexts recovered in challenges 4 and 6 as raw bytes,
+    /// instead of text with invalid UTF-8 replaced
+    #[arg(long)]
+    raw: bool,
+}
+
+/// Key sizes tried when breaking repeating-key XOR
+#[derive(Args)]
+pub struct KeySizeArgs {
+    /// Statistic ranking the key sizes
+    #[arg(long, value_enum, default_value = "hamming")]
+    key_size_method: KeySizeStatistic,
+    /// Smallest key size tried
+    #[arg(long, default_value_t = 2)]
+    min_key_size: usize,
+    /// Largest key size tried
+    #[arg(long, default_value_t = 40)]
+    max_key_size: usize,
+    /// Number of best key sizes fully decrypted
+    #[arg(long, default_value_t = 3)]
+    key_size_trials: usize,
+}
+
+impl KeySizeArgs {
+    pub fn options(&self) -> KeySizeOptions {
+        KeySizeOptions {
+            min_size: self.min_key_size,
+            max_size: self.max_key_size,
+            method: self.key_size_method.into(),
+            trials: self.key_size_trials,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum KeySizeStatistic {
+    /// Normalized Hamming distance between blocks
+    Hamming,
+    /// Index of coincidence
+    Ioc,
+}
+
+impl From<KeySizeStatistic> for KeySizeMethod {
+    fn from(statistic: KeySizeStatistic) -> KeySizeMethod {
+        match statistic {
+            KeySizeStatistic::Hamming => KeySizeMethod::Hamming,
+            KeySizeStatistic::Ioc => KeySizeMethod::IndexOfCoincidence,
+        }
+    }
+}
+
+impl RunArgs {
+    /// The user supplied input, or the challenge's default input
+    fn input_or(&self, default: &[u8]) -> Result<Vec<u8>> {
+        match &self.input {
+            Some(path) => read_input(Some(path)),
+            None => Ok(default.to_vec()),
+        }
+    }
+
+    /// The user supplied input file, or the challenge's data file
+    fn input_file_or(&self, default: &str) -> Result<Vec<u8>> {
+        match &self.input {
+            Some(path) => read_input(Some(path)),
+            None => read_input(Some(&Path::new(ROOT).join(default))),
+        }
+    }
+
+    fn key_or(&self, default: &str) -> Vec<u8> {
+        self.key.as_deref().unwrap_or(default).as_bytes().to_vec()
+    }
+
+    /// The user supplied key, or the all zero key of challenges 12 and 13
+    fn key_or_zero(&self) -> Vec<u8> {
+        match &self.key {
+            Some(key) => key.as_bytes().to_vec(),
+            None => vec![0; BLOCK_SIZE],
+        }
+    }
+
+    /// Print the language the plaintext fits best when it was picked automatically
+    fn print_language(&self, profiles: Option<&ProfileRegistry>, plaintext: &[u8]) {
+        if !self.raw {
+            if let Some(language) = self.scoring.detect_language(profiles, plaintext) {
+                println!("language: {}", language);
+            }
+        }
+    }
+
+    fn print_plaintext(&self, plaintext: &[u8]) -> Result<()> {
+        if self.raw {
+            io::stdout().write_all(plaintext)?;
+        } else {
+            println!("plaintext: {}", String::from_utf8_lossy(plaintext));
+        }
+        Ok(())
+    }
+}
+
+fn text(data: &[u8]) -> String {
+    String::from_utf8_lossy(data).trim().to_string()
+}
+
+pub fn run(args: &RunArgs) -> Result<()> {
+    let profiles = args.scoring.profiles()?;
+    let profiles = profiles.as_ref();
+    match args.challenge {
+        1 => {
+            let hex = text(&args.input_or(b"49276d206b696c6c696e6720796f757220627261696e206c696b65206120706f69736f6e6f7573206d757368726f6f6d")?);
+            println!("base64: {}", hex2base64(&hex)?);
+        }
+        2 => {
+            let x = decode(
+                &args.input_or(b"1c0111001f010100061a024b53535009181c")?,
+                Encoding::Hex,
+            )?;
+            let y = hex_decode(args.key_or("686974207468652062756c6c277320657965"))?;
+            println!("xor: {}", hex_encode(xor(&x, &y)?));
+        }
+        3 => {
+            let ciphertext = decode(
+                &args.input_or(
+                    b"1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736",
+                )?,
+                Encoding::Hex,
+            )?;
+            let candidates = rank_candidates(
+                &ciphertext,
+                args.scoring.scorer(profiles)?.as_ref(),
+                args.top,
+            );
+            if let Some(best) = candidates.first() {
+                args.print_language(profiles, &best.plaintext);
+            }
+            for candidate in candidates {
+                println!(
+                    "key {:#04x}, score {:.3}: {:?}",
+                    candidate.key,
+                    candidate.score,
+                    candidate.text()
+                );
+            }
+        }
+        4 => {
+            let lines = args.input_file_or("set1/chal4/data/hex_vals.csv")?;
+            let scorer = args.scoring.scorer(profiles)?;
+            let summary = scan_lines(&lines[..], scorer.as_ref(), &ScanOptions::default(), |_| {})?;
+            let best = match summary.results.first() {
+                Some(best) => best,
+                None => {
+                    println!("Did not find any results.");
+                    return Ok(());
+                }
+            };
+            if !args.raw {
+                println!("ciphertext: {}", hex_encode(&best.ciphertext));
+            }
+            args.print_language(profiles, &best.candidate.plaintext);
+            args.print_plaintext(&best.candidate.plaintext)?;
+        }
+        5 => {
+            let plaintext = args.input_or(
+                b"Burning 'em, if you ain't quick and nimble\nI go crazy when I hear a cymbal",
+            )?;
+            let ciphertext = repeating_key_xor(&plaintext, &args.key_or("ICE"))?;
+            println!("ciphertext: {}", hex_encode(ciphertext));
+        }
+        6 => {
+            let ciphertext = decode(
+                &args.input_file_or("set1/chal6/data/ciphertext.txt")?,
+                Encoding::Base64,
+            )?;
+            let solution = break_repeating_key_xor_with_options(
+                ciphertext,
+                args.scoring.scorer(profiles)?.as_ref(),
+                &args.key_sizes.options(),
+            )?;
+            let plaintext = solution.plaintext;
+            if !args.raw {
+                println!(
+                    "key ({} bytes): {:?} ({})",
+                    solution.key.len(),
+                    String::from_utf8_lossy(&solution.key),
+                    hex_encode(&solution.key)
+                );
+            }
+            args.print_language(profiles, &plaintext);
+            args.print_plaintext(&plaintext)?;
+        }
+        7 => {
+            let ciphertext = decode(
+                &args.input_file_or("set1/chal7/data/ciphertext.txt")?,
+                Encoding::Base64,
+            )?;
+            let plaintext = decrypt_aes_128_ecb(
+                &ciphertext,
+                &args.key_or("YELLOW SUBMARINE"),
+                Padding::Pkcs7,
+            )?;
+            println!("{}", String::from_utf8_lossy(&plaintext));
+        }
+        8 => {
+            let lines = args.input_file_or("set1/chal8/data/hex_values.txt")?;
+            let mut found = false;
+            for line in text(&lines).lines() {
+                if repeated_blocks(&hex_decode(line.trim())?, BLOCK_SIZE)? {
+                    println!("{}", line.trim());
+                    found = true;
+                }
+            }
+            if !found {
+                println!("Did not find any results.");
+            }
+        }
+        9 => {
+            let message = args.input_or(b"YELLOW SUBMARINE")?;
+            let padded_message = pkcs7(&message, args.block_size.unwrap_or(20))?;
+            println!("{:?}", String::from_utf8_lossy(&padded_message));
+        }
+        10 => {
+            let ciphertext = decode(
+                &args.input_file_or("set2/chal10/data/ciphertext.txt")?,
+                Encoding::Base64,
+            )?;
+            let iv = parse_iv(args.iv.as_deref())?;
+            let plaintext = decrypt_aes_128_cbc(
+                &ciphertext,
+                &iv,
+                &args.key_or("YELLOW SUBMARINE"),
+                Padding::Pkcs7,
+            )?;
+            println!("{}", String::from_utf8_lossy(&plaintext));
+        }
+        11 => {
+            let message = args.input_or(&[42; 4 * BLOCK_SIZE])?;
+            let seed = args.seed.unwrap_or_else(|| rand::thread_rng().gen());
+            println!("seed: {}", seed);
+            let mut rng = seeded_rng(seed);
+            let mut correct = 0;
+            for _ in 0..CHALLENGE_11_ROUNDS {
+                let mode = random_mode(&mut rng);
+                let ciphertext = encryption_oracle(&mut rng, &message, mode)?;
+                if detect_mode(&ciphertext)? == mode {
+                    correct += 1;
+                }
+            }
+            println!("correct guesses: {}/{}", correct, CHALLENGE_11_ROUNDS);
+        }
+        12 => {
+            let secret = decode(
+                &args.input_or(CHALLENGE_12_SECRET.as_bytes())?,
+                Encoding::Base64,
+            )?;
+            let oracle = EcbOracle::new(&args.key_or_zero(), &secret);
+            let (blocksize, plaintext) = break_ecb(&oracle)?;
+            println!("blocksize: {}", blocksize);
+            println!("{}", String::from_utf8_lossy(&plaintext));
+        }
+        13 => {
+            let email = text(&args.input_or(b"foo@bar.com")?);
+            let key = args.key_or_zero();
+            let ciphertext = encrypt_profile(&email, &key)?;
+            println!("ciphertext: {}", hex_encode(&ciphertext));
+            let mut profile: Vec<_> = decrypt_and_parse_profile(&ciphertext, &key)?
+                .into_iter()
+                .collect();
+            profile.sort();
+            for (name, value) in profile {
+                println!("{}: {}", name, value);
+            }
+        }
+        _ => unreachable!("challenge number is validated by clap"),
+    }
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use crate::read_input;
+use crate::scorer::ScorerArgs;
+use clap::{Args, ValueEnum};
+use cryptopals::classical::{
+    autokey_decrypt, autokey_encrypt, break_affine_with, break_autokey_with, break_caesar_with,
+    break_vigenere_with, caesar_decrypt, caesar_encrypt, vigenere_decrypt, vigenere_encrypt,
+    AffineKey, KeyLengthOptions,
+};
+use cryptopals::{Error, Result};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ClassicalArgs {
+    #[arg(value_enum)]
+    cipher: Cipher,
+    #[arg(value_enum)]
+    operation: Operation,
+    /// Key: a shift for caesar, letters for vigenere and autokey,
+    /// `a,b` for affine. Not needed to break a ciphertext.
+    #[arg(long)]
+    key: Option<String>,
+    /// Input file, stdin when omitted
+    #[arg(long)]
+    input: Option<PathBuf>,
+    /// Longest Vigenère key or autokey primer tried when breaking
+    #[arg(long, default_value_t = 20)]
+    max_key_len: usize,
+    #[command(flatten)]
+    scoring: ScorerArgs,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Cipher {
+    Caesar,
+    Vigenere,
+    Affine,
+    Autokey,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Operation {
+    Encrypt,
+    Decrypt,
+    /// Recover the key and plaintext of a ciphertext
+    Break,
+}
+
+impl ClassicalArgs {
+    fn key(&self) -> Result<&str> {
+        self.key
+            .as_deref()
+            .ok_or_else(|| Error::BadLength("--key is needed to encrypt or decrypt".to_string()))
+    }
+
+    fn shift(&self) -> Result<u8> {
+        let key = self.key()?;
+        key.trim()
+            .parse()
+            .map_err(|_| Error::InvalidEncoding(format!("Expected a shift, got {:?}", key)))
+    }
+
+    fn affine_key(&self) -> Result<AffineKey> {
+        let key = self.key()?;
+        let invalid = || Error::InvalidEncoding(format!("Expected a,b, got {:?}", key));
+        let (a, b) = key.split_once(',').ok_or_else(invalid)?;
+        let a = a.trim().parse().map_err(|_| invalid())?;
+        let b = b.trim().parse().map_err(|_| invalid())?;
+        AffineKey::new(a, b)
+    }
+}
+
+pub fn run(args: &ClassicalArgs) -> Result<()> {
+    let input = read_input(args.input.as_deref())?;
+    let output = match (args.operation, args.cipher) {
+        (Operation::Encrypt, Cipher::Caesar) => caesar_encrypt(&input, args.shift()?),
+        (Operation::Decrypt, Cipher::Caesar) => caesar_decrypt(&input, args.shift()?),
+        (Operation::Encrypt, Cipher::Vigenere) => vigenere_encrypt(&input, args.key()?.as_bytes())?,
+        (Operation::Decrypt, Cipher::Vigenere) => vigenere_decrypt(&input, args.key()?.as_bytes())?,
+        (Operation::Encrypt, Cipher::Affine) => args.affine_key()?.encrypt(&input),
+        (Operation::Decrypt, Cipher::Affine) => args.affine_key()?.decrypt(&input),
+        (Operation::Encrypt, Cipher::Autokey) => autokey_encrypt(&input, args.key()?.as_bytes())?,
+        (Operation::Decrypt, Cipher::Autokey) => autokey_decrypt(&input, args.key()?.as_bytes())?,
+        (Operation::Break, cipher) => {
+            let profiles = args.scoring.profiles()?;
+            let scorer = args.scoring.scorer(profiles.as_ref())?;
+            let options = KeyLengthOptions {
+                max_len: args.max_key_len,
+                ..KeyLengthOptions::default()
+            };
+            let (key, plaintext) = match cipher {
+                Cipher::Caesar => {
+                    let candidate = break_caesar_with(&input, scorer.as_ref());
+                    (candidate.key.to_string(), candidate.plaintext)
+                }
+                Cipher::Affine => {
+                    let (key, candidate) = break_affine_with(&input, scorer.as_ref());
+                    (format!("{},{}", key.a(), key.b()), candidate.plaintext)
+                }
+                Cipher::Vigenere | Cipher::Autokey => {
+                    let solution = if matches!(cipher, Cipher::Vigenere) {
+                        break_vigenere_with(&input, scorer.as_ref(), &options)?
+                    } else {
+                        break_autokey_with(&input, scorer.as_ref(), &options)?
+                    };
+                    (
+                        String::from_utf8_lossy(&solution.key).into_owned(),
+                        solution.plaintext,
+                    )
+                }
+            };
+            eprintln!("key: {}", key);
+            plaintext
+        }
+    };
+    io::stdout().write_all(&output)?;
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use cryptopals::crib::{CribMatch, CribSession};
+use cryptopals::encoding::hex_encode;
+use cryptopals::scoring::Scorer;
+use cryptopals::{Error, Result};
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Number of matches listed by `drag`
+const MATCHES_SHOWN: usize = 10;
+
+/// Number of ciphertexts whose fragments are listed for a match
+const FRAGMENTS_SHOWN: usize = 8;
+
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+const HELP: &str = "\
+Ciphertexts are numbered from 1, offsets start at 0.
+  show                      print the plaintexts revealed so far
+  drag <n> <crib>           slide a crib across ciphertext n and list
+                            the offsets revealing printable text
+  lock <n> <offset> <text>  lock in text as the plaintext of ciphertext n
+  unlock <offset> <len>     forget keystream bytes
+  key                       print the keystream, ?? for unknown bytes
+  help                      print this help
+  quit                      leave";
+
+/// Interactive crib dragging, reading commands from stdin
+pub struct CribShell<'a> {
+    session: CribSession,
+    scorer: &'a dyn Scorer,
+    color: bool,
+}
+
+impl<'a> CribShell<'a> {
+    pub fn new(session: CribSession, scorer: &'a dyn Scorer) -> CribShell<'a> {
+        CribShell {
+            session,
+            scorer,
+            color: io::stdout().is_terminal(),
+        }
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        println!(
+            "{} ciphertexts, type help for the commands",
+            self.session.ciphertexts().len()
+        );
+        self.show()?;
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        loop {
+            print!("> ");
+            io::stdout().flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+            match self.execute(&line) {
+                Ok(true) => continue,
+                Ok(false) => return Ok(()),
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+    }
+
+    /// Run a command, returning false when the shell should stop
+    fn execute(&mut self, line: &str) -> Result<bool> {
+        let (command, args) = match line.find(' ') {
+            Some(i) => (&line[..i], &line[i + 1..]),
+            None => (line.trim(), ""),
+        };
+        match command {
+            "" => {}
+            "show" => self.show()?,
+            "drag" => {
+                let (n, crib) = split_number(args)?;
+                self.drag(self.index(n)?, crib.as_bytes())?;
+            }
+            "lock" => {
+                let (n, rest) = split_number(args)?;
+                let (offset, text) = split_number(rest)?;
+                self.session.lock(self.index(n)?, offset, text.as_bytes())?;
+                self.show()?;
+            }
+            "unlock" => {
+                let (offset, rest) = split_number(args)?;
+                let (len, _) = split_number(rest)?;
+                self.session.unlock(offset, len);
+                self.show()?;
+            }
+            "key" => {
+                let key: Vec<String> = self
+                    .session
+                    .keystream()
+                    .iter()
+                    .map(|k| k.map_or("??".to_string(), |k| hex_encode([k])))
+                    .collect();
+                println!("{}", key.concat());
+            }
+            "help" => println!("{}", HELP),
+            "quit" | "exit" => return Ok(false),
+            _ => println!("Unknown command {:?}, type help for the commands", command),
+        }
+        Ok(true)
+    }
+
+    /// Convert a ciphertext number given by the user into an index
+    fn index(&self, n: usize) -> Result<usize> {
+        if n == 0 || n > self.session.ciphertexts().len() {
+            return Err(Error::BadLength(format!(
+                "Ciphertexts are numbered from 1 to {}",
+                self.session.ciphertexts().len()
+            )));
+        }
+        Ok(n - 1)
+    }
+
+    fn show(&self) -> Result<()> {
+        for i in 0..self.session.ciphertexts().len() {
+            let mut line = String::new();
+            for byte in self.session.plaintext(i)? {
+                match byte {
+                    Some(b) if b == b' ' || b.is_ascii_graphic() => {
+                        self.push_colored(&mut line, GREEN, &(b as char).to_string())
+                    }
+                    Some(_) => self.push_colored(&mut line, GREEN, "."),
+                    None => line.push('_'),
+                }
+            }
+            println!("{:3}: {}", i + 1, line);
+        }
+        Ok(())
+    }
+
+    fn drag(&self, index: usize, crib: &[u8]) -> Result<()> {
+        let mut matches: Vec<CribMatch> = self
+            .session
+            .drag(index, crib, self.scorer)?
+            .into_iter()
+            .filter(|m| m.printable)
+            .collect();
+        matches.sort_by(|a, b| {
+            a.candidate
+                .score
+                .partial_cmp(&b.candidate.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        if matches.is_empty() {
+            println!("No offset reveals printable text");
+        }
+        for m in matches.iter().take(MATCHES_SHOWN) {
+            let mut line = format!("offset {:4}  score {:7.3} ", m.offset, m.candidate.score);
+            for (i, fragment) in m.fragments.iter().enumerate().take(FRAGMENTS_SHOWN) {
+                line.push_str(" |");
+                let text = format!("{:?}", String::from_utf8_lossy(fragment));
+                if i == index {
+                    self.push_colored(&mut line, YELLOW, &text);
+                } else {
+                    line.push_str(&text);
+                }
+            }
+            if m.fragments.len() > FRAGMENTS_SHOWN {
+                line.push_str(" | ...");
+            }
+            println!("{}", line);
+        }
+        Ok(())
+    }
+
+    fn push_colored(&self, line: &mut String, color: &str, text: &str) {
+        if self.color {
+            line.push_str(color);
+            line.push_str(text);
+            line.push_str(RESET);
+        } else {
+            line.push_str(text);
+        }
+    }
+}
+
+/// Split a leading number off the arguments of a command,
+/// returning it along with the rest after a single space
+fn split_number(args: &str) -> Result<(usize, &str)> {
+    let (number, rest) = match args.find(' ') {
+        Some(i) => (&args[..i], &args[i + 1..]),
+        None => (args, ""),
+    };
+    let number = number
+        .trim()
+        .parse()
+        .map_err(|_| Error::InvalidEncoding(format!("Expected a number, got {:?}", number)))?;
+    Ok((number, rest))
+}
@@ -0,0 +1,727 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use cryptopals::crib::CribSession;
+use cryptopals::ctr::{aes_ctr, CounterLayout, Endianness};
+use cryptopals::encoding::{
+    base64_decode, base64_encode, hex_decode, hex_encode, hex_encode_upper,
+};
+use cryptopals::encoding::{Alphabet, Base64Decoder, DecodeReader, HexDecoder, STANDARD, URL_SAFE};
+use cryptopals::known_plaintext::{recover_key, recover_key_auto, Signature, SIGNATURES};
+use cryptopals::many_time_pad::{self, Alignment};
+use cryptopals::modes::{
+    aes_128, aes_128_ofb, decrypt_aes_128_cfb, encrypt_aes_128_cfb, CfbSegment, BLOCK_SIZE,
+};
+use cryptopals::ngram::NgramModel;
+use cryptopals::padding::Padding;
+use cryptopals::scan::{scan_lines, ScanOptions, ScanResult};
+use cryptopals::stream::{BlockMode, Decryptor, Encryptor};
+use cryptopals::xor::XorReader;
+use cryptopals::{Error, Result};
+use std::fs::{self, File};
+use std::io::{self, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+mod challenges;
+mod classical;
+mod crib;
+mod scorer;
+
+/// Size of the chunks encoded at a time by `EncodeWriter`,
+/// a multiple of 3 so base64 is only padded at the end
+const STREAM_CHUNK_SIZE: usize = 3 * 8192;
+
+/// Command-line front end for the cryptopals challenges and primitives
+#[derive(Parser)]
+#[command(name = "cryptopals")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a challenge, optionally on other inputs than the original ones
+    Run(challenges::RunArgs),
+    /// Train an n-gram model for scoring plaintexts, see `run --model`
+    TrainModel {
+        /// Corpus of text resembling the expected plaintexts
+        #[arg(long)]
+        corpus: PathBuf,
+        /// Length of the longest n-grams
+        #[arg(long, default_value_t = 3)]
+        order: usize,
+        /// File the model is written to
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Break every line of hex ciphertext as single-byte XOR
+    /// and list the lines with the best plaintexts
+    Scan {
+        /// Files of hex encoded ciphertexts, one per line, stdin when omitted
+        inputs: Vec<PathBuf>,
+        /// Number of lines listed
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        /// Number of worker threads, one per core by default
+        #[arg(long, default_value_t = 0)]
+        threads: usize,
+        /// Do not report progress on stderr
+        #[arg(long)]
+        quiet: bool,
+        #[command(flatten)]
+        scoring: scorer::ScorerArgs,
+    },
+    /// Recover the keystream shared by ciphertexts encrypted with
+    /// a reused one-time pad or CTR mode with a fixed nonce
+    ManyTimePad {
+        /// File with one ciphertext per line, stdin when omitted
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Encoding of the ciphertexts
+        #[arg(long, value_enum, default_value = "base64")]
+        input_encoding: Encoding,
+        /// Cut the ciphertexts to the length of the shortest one
+        #[arg(long)]
+        truncate: bool,
+        #[command(flatten)]
+        scoring: scorer::ScorerArgs,
+    },
+    /// Interactive crib dragging over ciphertexts sharing a keystream
+    CribDrag {
+        /// File with one ciphertext per line, stdin is used for the commands
+        #[arg(long)]
+        input: PathBuf,
+        /// Encoding of the ciphertexts
+        #[arg(long, value_enum, default_value = "base64")]
+        input_encoding: Encoding,
+        /// Start from the keystream bytes the many-time-pad solver
+        /// recovers with at least this confidence, from 0 to 1
+        #[arg(long)]
+        seed: Option<f32>,
+        #[command(flatten)]
+        scoring: scorer::ScorerArgs,
+    },
+    /// Recover the repeating XOR key of an encrypted file from the bytes
+    /// its format is known to have, such as its magic number
+    KnownPlaintext {
+        /// Encrypted file, stdin when omitted
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Encoding of the input data
+        #[arg(long, value_enum, default_value = "raw")]
+        input_encoding: Encoding,
+        /// Format of the file, one of png, zip, pdf, elf64, elf32, gzip,
+        /// gif and jpeg, detected from the ciphertext when omitted
+        #[arg(long)]
+        format: Option<String>,
+        #[command(flatten)]
+        key_sizes: challenges::KeySizeArgs,
+        /// File the decrypted data is written to
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Repeating-key XOR (encryption and decryption are the same operation).
+    /// The input is processed as a stream, so it can be of any size.
+    Xor {
+        /// Key of any length
+        #[arg(long)]
+        key: String,
+        /// Encoding of the key
+        #[arg(long, value_enum, default_value = "raw")]
+        key_encoding: Encoding,
+        #[command(flatten)]
+        io: IoArgs,
+    },
+    /// Caesar, Vigenère, affine and autokey ciphers over letters,
+    /// leaving other bytes as they are
+    Classical(classical::ClassicalArgs),
+    /// AES-128 in ECB mode.
+    /// The input is processed as a stream, so it can be of any size.
+    AesEcb {
+        #[arg(value_enum)]
+        operation: Operation,
+        /// 16 byte key, taken as literal bytes
+        #[arg(long)]
+        key: String,
+        /// Padding scheme
+        #[arg(long, value_enum, default_value = "pkcs7")]
+        padding: PaddingScheme,
+        #[command(flatten)]
+        io: IoArgs,
+    },
+    /// AES-128 in CBC mode.
+    /// The input is processed as a stream, so it can be of any size.
+    AesCbc {
+        #[arg(value_enum)]
+        operation: Operation,
+        /// 16 byte key, taken as literal bytes
+        #[arg(long)]
+        key: String,
+        /// Hex encoded IV, all zeros by default
+        #[arg(long)]
+        iv: Option<String>,
+        /// Padding scheme
+        #[arg(long, value_enum, default_value = "pkcs7")]
+        padding: PaddingScheme,
+        #[command(flatten)]
+        io: IoArgs,
+    },
+    /// AES-128 in CFB mode, without padding
+    AesCfb {
+        #[arg(value_enum)]
+        operation: Operation,
+        /// 16 byte key, taken as literal bytes
+        #[arg(long)]
+        key: String,
+        /// Hex encoded IV, all zeros by default
+        #[arg(long)]
+        iv: Option<String>,
+        /// Bits encrypted at a time
+        #[arg(long, value_enum, default_value = "128")]
+        segment: Segment,
+        #[command(flatten)]
+        io: IoArgs,
+    },
+    /// AES-128 in OFB mode (encryption and decryption are the same operation)
+    AesOfb {
+        /// 16 byte key, taken as literal bytes
+        #[arg(long)]
+        key: String,
+        /// Hex encoded IV, all zeros by default
+        #[arg(long)]
+        iv: Option<String>,
+        #[command(flatten)]
+        io: IoArgs,
+    },
+    /// AES in CTR mode (encryption and decryption are the same operation)
+    AesCtr {
+        /// 16, 24 or 32 byte key, taken as literal bytes
+        #[arg(long)]
+        key: String,
+        /// Hex encoded nonce, filling the block before the counter,
+        /// all zeros by default
+        #[arg(long)]
+        nonce: Option<String>,
+        /// Width of the counter in bytes
+        #[arg(long, default_value_t = 8)]
+        counter_len: usize,
+        /// Byte order of the counter
+        #[arg(long, value_enum, default_value = "little")]
+        counter_endianness: CounterEndianness,
+        #[command(flatten)]
+        io: IoArgs,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Operation {
+    Encrypt,
+    Decrypt,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Encoding {
+    Raw,
+    Hex,
+    /// Uppercase hex, same as hex when decoding
+    HexUpper,
+    Base64,
+    /// URL-safe base64
+    Base64Url,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Segment {
+    #[value(name = "1")]
+    Bit,
+    #[value(name = "8")]
+    Byte,
+    #[value(name = "128")]
+    Block,
+}
+
+impl From<Segment> for CfbSegment {
+    fn from(segment: Segment) -> CfbSegment {
+        match segment {
+            Segment::Bit => CfbSegment::Bit,
+            Segment::Byte => CfbSegment::Byte,
+            Segment::Block => CfbSegment::Block,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CounterEndianness {
+    Big,
+    Little,
+}
+
+impl From<CounterEndianness> for Endianness {
+    fn from(endianness: CounterEndianness) -> Endianness {
+        match endianness {
+            CounterEndianness::Big => Endianness::Big,
+            CounterEndianness::Little => Endianness::Little,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PaddingScheme {
+    /// No padding, the input must be made of whole blocks
+    None,
+    Pkcs7,
+    AnsiX923,
+    Iso10126,
+    Iso7816,
+    /// Zero bytes, trailing zeros of the plaintext are lost
+    Zero,
+}
+
+impl From<PaddingScheme> for Padding {
+    fn from(scheme: PaddingScheme) -> Padding {
+        match scheme {
+            PaddingScheme::None => Padding::None,
+            PaddingScheme::Pkcs7 => Padding::Pkcs7,
+            PaddingScheme::AnsiX923 => Padding::AnsiX923,
+            PaddingScheme::Iso10126 => Padding::Iso10126,
+            PaddingScheme::Iso7816 => Padding::Iso7816,
+            PaddingScheme::Zero => Padding::Zero,
+        }
+    }
+}
+
+#[derive(Args)]
+struct IoArgs {
+    /// Input file, stdin when omitted
+    #[arg(long)]
+    input: Option<PathBuf>,
+    /// Encoding of the input data
+    #[arg(long, value_enum, default_value = "raw")]
+    input_encoding: Encoding,
+    /// Encoding of the output data
+    #[arg(long, value_enum, default_value = "hex")]
+    output_encoding: Encoding,
+}
+
+impl IoArgs {
+    fn read(&self) -> Result<Vec<u8>> {
+        decode(&read_input(self.input.as_deref())?, self.input_encoding)
+    }
+
+    /// Reader over the decoded input, for commands streaming their input
+    fn reader(&self) -> Result<Box<dyn Read>> {
+        let input: Box<dyn Read> = match &self.input {
+            Some(path) => Box::new(File::open(path)?),
+            None => Box::new(io::stdin()),
+        };
+        Ok(match self.input_encoding {
+            Encoding::Raw => input,
+            Encoding::Hex | Encoding::HexUpper => {
+                Box::new(DecodeReader::new(input, HexDecoder::new()))
+            }
+            Encoding::Base64 => Box::new(DecodeReader::new(
+                input,
+                Base64Decoder::new(Alphabet::Standard),
+            )),
+            Encoding::Base64Url => Box::new(DecodeReader::new(
+                input,
+                Base64Decoder::new(Alphabet::UrlSafe),
+            )),
+        })
+    }
+
+    fn write(&self, data: &[u8]) -> Result<()> {
+        write_output(data, self.output_encoding)
+    }
+}
+
+/// Read the whole input file, or stdin if no file is given
+fn read_input(path: Option<&Path>) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    match path {
+        Some(path) => File::open(path)?.read_to_end(&mut data)?,
+        None => io::stdin().read_to_end(&mut data)?,
+    };
+    Ok(data)
+}
+
+/// Decode input data, ignoring whitespace for the text encodings
+fn decode(data: &[u8], encoding: Encoding) -> Result<Vec<u8>> {
+    match encoding {
+        Encoding::Raw => Ok(data.to_vec()),
+        Encoding::Hex | Encoding::HexUpper => hex_decode(data),
+        Encoding::Base64 => base64_decode(data, STANDARD),
+        Encoding::Base64Url => base64_decode(data, URL_SAFE),
+    }
+}
+
+fn write_output(data: &[u8], encoding: Encoding) -> Result<()> {
+    let mut stdout = io::stdout();
+    write_encoded(&mut stdout, data, encoding)?;
+    if !matches!(encoding, Encoding::Raw) {
+        writeln!(stdout)?;
+    }
+    Ok(())
+}
+
+/// Write encoded data, without a trailing newline
+fn write_encoded<W: Write>(output: &mut W, data: &[u8], encoding: Encoding) -> Result<()> {
+    match encoding {
+        Encoding::Raw => output.write_all(data)?,
+        Encoding::Hex => write!(output, "{}", hex_encode(data))?,
+        Encoding::HexUpper => write!(output, "{}", hex_encode_upper(data))?,
+        Encoding::Base64 => write!(output, "{}", base64_encode(data, STANDARD))?,
+        Encoding::Base64Url => write!(output, "{}", base64_encode(data, URL_SAFE))?,
+    }
+    Ok(())
+}
+
+/// Writer encoding everything written to it, one chunk at a time.
+/// `finish` must be called to write the last chunk.
+struct EncodeWriter<W: Write> {
+    inner: W,
+    encoding: Encoding,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> EncodeWriter<W> {
+    fn new(inner: W, encoding: Encoding) -> EncodeWriter<W> {
+        EncodeWriter {
+            inner,
+            encoding,
+            pending: Vec::with_capacity(STREAM_CHUNK_SIZE),
+        }
+    }
+
+    /// Encode the remaining data and end the text encodings with a newline
+    fn finish(mut self) -> Result<()> {
+        write_encoded(&mut self.inner, &self.pending, self.encoding)?;
+        if !matches!(self.encoding, Encoding::Raw) {
+            writeln!(self.inner)?;
+        }
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for EncodeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(STREAM_CHUNK_SIZE - self.pending.len());
+        self.pending.extend_from_slice(&buf[..n]);
+        if self.pending.len() == STREAM_CHUNK_SIZE {
+            write_encoded(&mut self.inner, &self.pending, self.encoding)?;
+            self.pending.clear();
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writer encoding to stdout
+fn stdout_encoder(encoding: Encoding) -> EncodeWriter<io::BufWriter<io::Stdout>> {
+    EncodeWriter::new(io::BufWriter::new(io::stdout()), encoding)
+}
+
+/// Copy a reader to stdout in the given encoding
+fn stream_output<R: Read>(mut input: R, encoding: Encoding) -> Result<()> {
+    let mut output = stdout_encoder(encoding);
+    io::copy(&mut input, &mut output)?;
+    output.finish()
+}
+
+/// Encrypt or decrypt the input with AES-128 in ECB or CBC mode as a stream
+fn stream_aes_128(
+    operation: Operation,
+    key: &str,
+    mode: BlockMode,
+    padding: Padding,
+    io: &IoArgs,
+) -> Result<()> {
+    let cipher = aes_128(key.as_bytes())?;
+    match operation {
+        Operation::Encrypt => {
+            let output = stdout_encoder(io.output_encoding);
+            let mut encryptor = Encryptor::new(output, cipher, mode, padding)?;
+            io::copy(&mut io.reader()?, &mut encryptor)?;
+            encryptor.finish(&mut rand::thread_rng())?.finish()
+        }
+        Operation::Decrypt => stream_output(
+            Decryptor::new(io.reader()?, cipher, mode, padding)?,
+            io.output_encoding,
+        ),
+    }
+}
+
+/// Parse a hex encoded IV, defaulting to an all zero block
+fn parse_iv(iv: Option<&str>) -> Result<Vec<u8>> {
+    match iv {
+        Some(iv) => hex_decode(iv),
+        None => Ok(vec![0; BLOCK_SIZE]),
+    }
+}
+
+/// Scan each input in turn and print the best lines over all of them
+fn scan(
+    inputs: &[PathBuf],
+    options: &ScanOptions,
+    quiet: bool,
+    scoring: &scorer::ScorerArgs,
+) -> Result<()> {
+    let profiles = scoring.profiles()?;
+    let scorer = scoring.scorer(profiles.as_ref())?;
+    let show_progress = !quiet && io::stderr().is_terminal();
+    let names: Vec<String> = match inputs {
+        [] => vec!["<stdin>".to_string()],
+        _ => inputs
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect(),
+    };
+    let mut results: Vec<(usize, ScanResult)> = Vec::new();
+    let mut skipped = 0;
+    for (index, name) in names.iter().enumerate() {
+        let progress = |lines: usize| {
+            if show_progress {
+                eprint!("\r{}: {} lines read", name, lines);
+            }
+        };
+        let summary = match inputs.get(index) {
+            Some(path) => scan_lines(
+                BufReader::new(File::open(path)?),
+                scorer.as_ref(),
+                options,
+                progress,
+            )?,
+            None => scan_lines(io::stdin().lock(), scorer.as_ref(), options, progress)?,
+        };
+        if show_progress {
+            eprintln!();
+        }
+        skipped += summary.skipped;
+        results.extend(summary.results.into_iter().map(|result| (index, result)));
+    }
+    results.sort_by(|(_, a), (_, b)| {
+        a.candidate
+            .score
+            .partial_cmp(&b.candidate.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    results.truncate(options.top);
+    if skipped > 0 {
+        eprintln!("Skipped {} lines that are not valid hex", skipped);
+    }
+    for (index, result) in results {
+        print!(
+            "{}:{}: key {:#04x}, score {:.3}: {:?}",
+            names[index],
+            result.line,
+            result.candidate.key,
+            result.candidate.score,
+            result.candidate.text()
+        );
+        match scoring.detect_language(profiles.as_ref(), &result.candidate.plaintext) {
+            Some(language) => println!(" ({})", language),
+            None => println!(),
+        }
+    }
+    Ok(())
+}
+
+/// Read a file holding one encoded ciphertext per line
+fn read_ciphertexts(input: Option<&Path>, encoding: Encoding) -> Result<Vec<Vec<u8>>> {
+    read_input(input)?
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.trim_ascii().is_empty())
+        .map(|line| decode(line, encoding))
+        .collect()
+}
+
+/// Solve a many-time pad and print the keystream and the plaintexts
+fn solve_many_time_pad(
+    input: Option<&Path>,
+    encoding: Encoding,
+    alignment: Alignment,
+    scoring: &scorer::ScorerArgs,
+) -> Result<()> {
+    let ciphertexts = read_ciphertexts(input, encoding)?;
+    let profiles = scoring.profiles()?;
+    let scorer = scoring.scorer(profiles.as_ref())?;
+    let solution = many_time_pad::solve_with(&ciphertexts, alignment, scorer.as_ref())?;
+    println!("keystream: {}", hex_encode(&solution.keystream));
+    for (i, plaintext) in solution.plaintexts.iter().enumerate() {
+        println!("{}: {:?}", i + 1, String::from_utf8_lossy(plaintext));
+    }
+    Ok(())
+}
+
+/// Recover the key of a file of a known format and print it
+fn known_plaintext(
+    ciphertext: &[u8],
+    format: Option<&str>,
+    key_sizes: &challenges::KeySizeArgs,
+    output: Option<&Path>,
+) -> Result<()> {
+    let options = key_sizes.options();
+    let (signature, solution) = match format {
+        Some(name) => {
+            let signature = Signature::find(name).ok_or_else(|| {
+                let names: Vec<&str> = SIGNATURES.iter().map(|s| s.name).collect();
+                Error::InvalidEncoding(format!(
+                    "Unknown format {:?}, expected one of {}",
+                    name,
+                    names.join(", ")
+                ))
+            })?;
+            (signature, recover_key(ciphertext, signature, &options)?)
+        }
+        None => recover_key_auto(ciphertext, &options)?,
+    };
+    let known = solution.confidence.iter().filter(|&&c| c == 1.0).count();
+    println!("format: {}", signature.name);
+    println!(
+        "key ({} bytes, {} known): {}",
+        solution.key_size(),
+        known,
+        hex_encode(&solution.key)
+    );
+    if let Some(path) = output {
+        fs::write(path, &solution.plaintext)?;
+    }
+    Ok(())
+}
+
+fn execute(command: Command) -> Result<()> {
+    match command {
+        Command::Run(args) => challenges::run(&args),
+        Command::TrainModel {
+            corpus,
+            order,
+            output,
+        } => NgramModel::train_file(corpus, order)?.save_file(output),
+        Command::Scan {
+            inputs,
+            top,
+            threads,
+            quiet,
+            scoring,
+        } => scan(&inputs, &ScanOptions { top, threads }, quiet, &scoring),
+        Command::ManyTimePad {
+            input,
+            input_encoding,
+            truncate,
+            scoring,
+        } => {
+            let alignment = if truncate {
+                Alignment::Truncate
+            } else {
+                Alignment::Columns
+            };
+            solve_many_time_pad(input.as_deref(), input_encoding, alignment, &scoring)
+        }
+        Command::CribDrag {
+            input,
+            input_encoding,
+            seed,
+            scoring,
+        } => {
+            let ciphertexts = read_ciphertexts(Some(&input), input_encoding)?;
+            let profiles = scoring.profiles()?;
+            let scorer = scoring.scorer(profiles.as_ref())?;
+            let mut session = CribSession::new(ciphertexts.clone())?;
+            if let Some(min_confidence) = seed {
+                let solution =
+                    many_time_pad::solve_with(&ciphertexts, Alignment::Columns, scorer.as_ref())?;
+                session.seed(&solution, min_confidence);
+            }
+            crib::CribShell::new(session, scorer.as_ref()).run()
+        }
+        Command::KnownPlaintext {
+            input,
+            input_encoding,
+            format,
+            key_sizes,
+            output,
+        } => {
+            let ciphertext = decode(&read_input(input.as_deref())?, input_encoding)?;
+            known_plaintext(
+                &ciphertext,
+                format.as_deref(),
+                &key_sizes,
+                output.as_deref(),
+            )
+        }
+        Command::Xor {
+            key,
+            key_encoding,
+            io,
+        } => {
+            let key = decode(key.as_bytes(), key_encoding)?;
+            stream_output(XorReader::new(io.reader()?, &key)?, io.output_encoding)
+        }
+        Command::Classical(args) => classical::run(&args),
+        Command::AesEcb {
+            operation,
+            key,
+            padding,
+            io,
+        } => stream_aes_128(operation, &key, BlockMode::Ecb, padding.into(), &io),
+        Command::AesCbc {
+            operation,
+            key,
+            iv,
+            padding,
+            io,
+        } => {
+            let iv = parse_iv(iv.as_deref())?;
+            stream_aes_128(operation, &key, BlockMode::Cbc { iv }, padding.into(), &io)
+        }
+        Command::AesCfb {
+            operation,
+            key,
+            iv,
+            segment,
+            io,
+        } => {
+            let input = io.read()?;
+            let iv = parse_iv(iv.as_deref())?;
+            let segment = segment.into();
+            let output = match operation {
+                Operation::Encrypt => encrypt_aes_128_cfb(&input, &iv, key.as_bytes(), segment)?,
+                Operation::Decrypt => decrypt_aes_128_cfb(&input, &iv, key.as_bytes(), segment)?,
+            };
+            io.write(&output)
+        }
+        Command::AesOfb { key, iv, io } => {
+            let iv = parse_iv(iv.as_deref())?;
+            io.write(&aes_128_ofb(&io.read()?, &iv, key.as_bytes())?)
+        }
+        Command::AesCtr {
+            key,
+            nonce,
+            counter_len,
+            counter_endianness,
+            io,
+        } => {
+            let layout = CounterLayout {
+                counter_len,
+                endianness: counter_endianness.into(),
+            };
+            let nonce = match nonce {
+                Some(nonce) => hex_decode(nonce)?,
+                None => vec![0; BLOCK_SIZE.saturating_sub(counter_len)],
+            };
+            io.write(&aes_ctr(&io.read()?, key.as_bytes(), &nonce, layout)?)
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = execute(cli.command) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
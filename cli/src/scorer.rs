@@ -0,0 +1,97 @@
+use crate::Cli;
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory};
+use cryptopals::ngram::NgramModel;
+use cryptopals::profiles::ProfileRegistry;
+use cryptopals::scoring::{LogLikelihood, Scorer};
+use cryptopals::Result;
+use std::path::PathBuf;
+
+/// Options choosing how candidate plaintexts are scored
+#[derive(Args)]
+pub struct ScorerArgs {
+    /// N-gram model scoring the plaintexts, instead of English letter frequencies
+    #[arg(long)]
+    model: Option<PathBuf>,
+    /// Language of the plaintexts, or `auto` to pick the language that fits best
+    #[arg(long)]
+    language: Option<String>,
+    /// Extra language profile file, see `Profile::from_file` (repeatable)
+    #[arg(long)]
+    profile: Vec<PathBuf>,
+}
+
+impl ScorerArgs {
+    /// The built-in language profiles and the ones given with `--profile`,
+    /// when a language is given or picked automatically. Build it once per
+    /// command and pass it to `scorer` and `detect_language`.
+    /// An unknown language is reported as a usage error.
+    pub fn profiles(&self) -> Result<Option<ProfileRegistry>> {
+        if self.model.is_some() || (self.language.is_none() && self.profile.is_empty()) {
+            return Ok(None);
+        }
+        let mut registry = ProfileRegistry::builtin();
+        for path in &self.profile {
+            registry.register_file(path)?;
+        }
+        if let Some(name) = &self.language {
+            if name != "auto" && registry.get(name).is_none() {
+                let names: Vec<&str> = registry
+                    .profiles()
+                    .iter()
+                    .map(|p| p.name.as_str())
+                    .collect();
+                Cli::command()
+                    .error(
+                        ErrorKind::InvalidValue,
+                        format!(
+                            "unknown language {:?} for '--language', expected auto or one of {}",
+                            name,
+                            names.join(", ")
+                        ),
+                    )
+                    .exit();
+            }
+        }
+        Ok(Some(registry))
+    }
+
+    fn is_auto(&self) -> bool {
+        self.model.is_none()
+            && (self.language.as_deref() == Some("auto")
+                || (self.language.is_none() && !self.profile.is_empty()))
+    }
+
+    /// The scorer chosen by the options, taking the language
+    /// profiles from the registry built by `profiles`
+    pub fn scorer(&self, profiles: Option<&ProfileRegistry>) -> Result<Box<dyn Scorer + Sync>> {
+        if let Some(path) = &self.model {
+            return Ok(Box::new(NgramModel::load_file(path)?));
+        }
+        let profiles = match profiles {
+            Some(profiles) => profiles,
+            None => return Ok(Box::new(LogLikelihood::default())),
+        };
+        if self.is_auto() {
+            return Ok(Box::new(profiles.clone()));
+        }
+        match self.language.as_deref().and_then(|name| profiles.get(name)) {
+            Some(profile) => Ok(Box::new(profile.clone())),
+            None => Ok(Box::new(LogLikelihood::default())),
+        }
+    }
+
+    /// The language the plaintext fits best, when it is picked automatically
+    pub fn detect_language<'a>(
+        &self,
+        profiles: Option<&'a ProfileRegistry>,
+        plaintext: &[u8],
+    ) -> Option<&'a str> {
+        if !self.is_auto() {
+            return None;
+        }
+        profiles?
+            .best_fit(plaintext)
+            .map(|profile| profile.name.as_str())
+    }
+}
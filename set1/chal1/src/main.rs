@@ -1,20 +1,14 @@
-extern crate base64;
-extern crate hex;
-use base64::encode;
-use hex::decode;
+use cryptopals::encoding::hex2base64;
+use cryptopals::Result;
 
-fn hex2base64(hex: &str) -> String {
-    let bytes = decode(hex).expect("Invalid hex string");
-    encode(bytes)
-}
-
-fn main() {
+fn main() -> Result<()> {
     let hex = "49276d206b696c6c696e6720796f757220627261696e206c696b65206120706f69736f6e6f7573206d757368726f6f6d";
-    let base64 = hex2base64(hex);
+    let base64 = hex2base64(hex)?;
     assert_eq!(
         base64,
         "SSdtIGtpbGxpbmcgeW91ciBicmFpbiBsaWtlIGEgcG9pc29ub3VzIG11c2hyb29t"
     );
     println!("hex: {}", hex);
     println!("base64 {}", base64);
+    Ok(())
 }
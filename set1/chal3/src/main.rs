@@ -1,88 +1,23 @@
-extern crate hex;
-use hex::decode;
-use std::collections::HashMap;
-use std::str;
+use cryptopals::encoding::hex_decode;
+use cryptopals::scoring::{rank_candidates, LogLikelihood};
+use cryptopals::Result;
 
-const CHARS: [char; 27] = [
-    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
-    't', 'u', 'v', 'w', 'x', 'y', 'z', ' ',
-];
-
-struct Candidate {
-    plaintext: String,
-    score: f32,
-    distribution: HashMap<char, f32>,
-}
-
-impl Candidate {
-    fn new(plaintext: String) -> Candidate {
-        Candidate {
-            plaintext,
-            score: std::f32::MAX,
-            distribution: HashMap::new(),
-        }
-    }
-    fn get_distribution(&mut self) {
-        for &c in CHARS.iter() {
-            let freq: f32 = get_frequency(&self.plaintext, c);
-            &self.distribution.insert(c, freq);
-        }
-    }
-    fn get_score(&mut self) {
-        self.get_distribution();
-        let mut score = 0_f32;
-        for (_c, f) in self.distribution.iter() {
-            score += f;
-        }
-        self.score = -score;
-    }
-}
-
-fn get_frequency(s: &String, c: char) -> f32 {
-    let mut count = 0_f32;
-    let mut total = 0_f32;
-    for s in s.chars() {
-        if s == c {
-            count += 1_f32;
-        }
-        total += 1_f32;
-    }
-    count / total
-}
-
-fn byte_xor(buffer: &Vec<u8>, byte: &u8) -> Candidate {
-    let mut xor: Vec<u8> = Vec::new();
-    for b in buffer.iter() {
-        xor.push(b ^ byte);
-    }
-    let result_string = str::from_utf8(&xor);
-    match result_string {
-        Ok(s) => {
-            let mut candidate = Candidate::new(s.to_string());
-            candidate.get_score();
-            return candidate;
-        }
-        _ => return Candidate::new(String::from("")),
-    }
-}
-
-fn brute_force(hex: &str) -> String {
-    let decoded_hex = decode(hex).expect("Invalid hex string");
-    let mut plaintext = String::new();
-    let mut best_score = std::f32::MAX;
-    for b in 0..=255 {
-        let candidate = byte_xor(&decoded_hex, &b);
-        if candidate.score < best_score {
-            best_score = candidate.score;
-            plaintext = candidate.plaintext;
-        }
-    }
-    plaintext
-}
-
-fn main() {
+fn main() -> Result<()> {
     let ciphertext = "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736";
-    let plaintext = brute_force(ciphertext);
+    let decoded_hex = hex_decode(ciphertext)?;
+    let candidates = rank_candidates(&decoded_hex, &LogLikelihood::default(), 3);
     println!("ciphertext: {}", ciphertext);
-    println!("plaintext: {}", plaintext);
+    if let Some(best) = candidates.first() {
+        println!("plaintext: {}", best.text());
+    }
+    println!("runners-up:");
+    for candidate in candidates.iter().skip(1) {
+        println!(
+            "  key {:#04x}, score {:.3}: {:?}",
+            candidate.key,
+            candidate.score,
+            candidate.text()
+        );
+    }
+    Ok(())
 }
@@ -1,23 +1,14 @@
-use hex::encode;
+use cryptopals::encoding::hex_encode;
+use cryptopals::xor::repeating_key_xor;
+use cryptopals::Result;
 
-fn vigenere(plaintext: &str, key: &str) -> String {
-    let p_bytes = plaintext.as_bytes();
-    let n = p_bytes.len();
-    let k_bytes = key.as_bytes();
-    let mut xor: Vec<u8> = Vec::with_capacity(n);
-    for i in 0..n {
-        let key_index = i % 3;
-        xor.push(p_bytes[i] ^ k_bytes[key_index]);
-    }
-    encode(xor)
-}
-
-fn main() {
-    let plaintext = "Burning 'em, if you ain't quick and nimble
+fn main() -> Result<()> {
+    let plaintext = b"Burning 'em, if you ain't quick and nimble
 I go crazy when I hear a cymbal";
-    let key = "ICE";
-    let ciphertext = vigenere(plaintext, key);
+    let key = b"ICE";
+    let ciphertext = hex_encode(repeating_key_xor(plaintext, key)?);
     assert_eq!(ciphertext, "0b3637272a2b2e63622c2e69692a23693a2a3c6324202d623d63343c2a26226324272765272a282b2f20430a652e2c652a3124333a653e2b2027630c692b20283165286326302e27282f");
-    println!("plaintext: {}", plaintext);
+    println!("plaintext: {}", String::from_utf8_lossy(plaintext));
     println!("ciphertext: {}", ciphertext);
+    Ok(())
 }
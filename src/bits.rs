@@ -0,0 +1,333 @@
+//! Bit-level analysis of buffers and ciphers.
+//!
+//! Buffers are processed a 64-bit word at a time, with the remaining
+//! bytes handled one by one, and bits are counted with `count_ones`,
+//! which compiles to a single popcount instruction where the target has
+//! one. Bit `i` of a buffer is bit `7 - i % 8` of byte `i / 8`, so bits
+//! are numbered from the most significant bit of the first byte.
+use crate::cipher::BlockCipher;
+use crate::error::{Error, Result};
+use rand::Rng;
+use std::convert::TryInto;
+
+/// Bytes in a word
+const WORD_SIZE: usize = 8;
+
+fn word(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes(bytes.try_into().expect("chunk of a word"))
+}
+
+fn check_lengths(x: &[u8], y: &[u8]) -> Result<()> {
+    if x.len() != y.len() {
+        return Err(Error::BadLength(format!(
+            "Buffers of {} and {} bytes cannot be compared bit by bit",
+            x.len(),
+            y.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Number of bits set in a buffer
+pub fn weight(data: &[u8]) -> u64 {
+    let mut chunks = data.chunks_exact(WORD_SIZE);
+    let words: u64 = chunks
+        .by_ref()
+        .map(|chunk| word(chunk).count_ones() as u64)
+        .sum();
+    let rest: u64 = chunks
+        .remainder()
+        .iter()
+        .map(|b| b.count_ones() as u64)
+        .sum();
+    words + rest
+}
+
+/// Number of differing bits between two buffers of the same length
+pub fn distance(x: &[u8], y: &[u8]) -> Result<u64> {
+    check_lengths(x, y)?;
+    let mut xs = x.chunks_exact(WORD_SIZE);
+    let mut ys = y.chunks_exact(WORD_SIZE);
+    let words: u64 = xs
+        .by_ref()
+        .zip(ys.by_ref())
+        .map(|(a, b)| (word(a) ^ word(b)).count_ones() as u64)
+        .sum();
+    let rest: u64 = xs
+        .remainder()
+        .iter()
+        .zip(ys.remainder())
+        .map(|(a, b)| (a ^ b).count_ones() as u64)
+        .sum();
+    Ok(words + rest)
+}
+
+/// Positions of the bits that differ between two buffers of the
+/// same length, in increasing order
+pub fn differing_bits(x: &[u8], y: &[u8]) -> Result<Vec<usize>> {
+    check_lengths(x, y)?;
+    let mut positions = Vec::new();
+    for (i, (a, b)) in x.chunks(WORD_SIZE).zip(y.chunks(WORD_SIZE)).enumerate() {
+        if a == b {
+            continue;
+        }
+        for (j, (a, b)) in a.iter().zip(b).enumerate() {
+            let mut diff = a ^ b;
+            while diff != 0 {
+                let bit = diff.leading_zeros() as usize;
+                positions.push((i * WORD_SIZE + j) * 8 + bit);
+                diff &= !(0x80 >> bit);
+            }
+        }
+    }
+    Ok(positions)
+}
+
+/// Value of bit `i` of a buffer
+pub fn get_bit(data: &[u8], i: usize) -> bool {
+    data[i / 8] & (0x80 >> (i % 8)) != 0
+}
+
+/// Flip bit `i` of a buffer
+pub fn flip_bit(data: &mut [u8], i: usize) {
+    data[i / 8] ^= 0x80 >> (i % 8);
+}
+
+/// How flipping single bits of the input of a block cipher
+/// flips the bits of its output
+#[derive(Debug, Clone)]
+pub struct Avalanche {
+    /// Number of random blocks tried
+    pub samples: usize,
+    /// `flips[i][j]` is the number of blocks for which flipping
+    /// input bit `i` flipped output bit `j`
+    pub flips: Vec<Vec<u32>>,
+}
+
+impl Avalanche {
+    /// Share of the output bits flipped by flipping one input bit,
+    /// 0.5 for a cipher with full diffusion
+    pub fn mean(&self) -> f64 {
+        let bits = self.flips.len() * self.flips.len();
+        let total: u64 = self.flips.iter().flatten().map(|&n| n as u64).sum();
+        total as f64 / (bits * self.samples) as f64
+    }
+
+    /// Probability that flipping input bit `input` flips output bit `output`
+    pub fn probability(&self, input: usize, output: usize) -> f64 {
+        self.flips[input][output] as f64 / self.samples as f64
+    }
+
+    /// Largest distance of any of the probabilities from 0.5. The strict
+    /// avalanche criterion asks for every one of them to be close to 0.
+    pub fn worst_deviation(&self) -> f64 {
+        self.flips
+            .iter()
+            .flatten()
+            .map(|&n| (n as f64 / self.samples as f64 - 0.5).abs())
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Measure the avalanche effect of a block cipher on `samples` random
+/// blocks, flipping every bit of each block in turn
+pub fn avalanche<C: BlockCipher + ?Sized, R: Rng + ?Sized>(
+    cipher: &C,
+    rng: &mut R,
+    samples: usize,
+) -> Result<Avalanche> {
+    if samples == 0 {
+        return Err(Error::BadLength(
+            "Avalanche needs at least one sample".to_string(),
+        ));
+    }
+    let bits = cipher.block_size() * 8;
+    let mut flips = vec![vec![0; bits]; bits];
+    for _ in 0..samples {
+        let mut block = crate::random::random_bytes(rng, cipher.block_size());
+        let mut encrypted = block.clone();
+        cipher.encrypt_block(&mut encrypted);
+        for (i, row) in flips.iter_mut().enumerate() {
+            flip_bit(&mut block, i);
+            let mut flipped = block.clone();
+            cipher.encrypt_block(&mut flipped);
+            flip_bit(&mut block, i);
+            let diff = differing_bits(&encrypted, &flipped).expect("blocks of the same size");
+            for j in diff {
+                row[j] += 1;
+            }
+        }
+    }
+    Ok(Avalanche { samples, flips })
+}
+
+/// Number of times each bit is set over samples of a fixed width,
+/// to find bits that are not set half of the time
+#[derive(Debug, Clone)]
+pub struct BitBias {
+    ones: Vec<u64>,
+    samples: u64,
+}
+
+impl BitBias {
+    /// Empty counts for samples of `width` bytes
+    pub fn new(width: usize) -> Result<BitBias> {
+        if width == 0 {
+            return Err(Error::BadLength(
+                "Samples must be at least one byte wide".to_string(),
+            ));
+        }
+        Ok(BitBias {
+            ones: vec![0; width * 8],
+            samples: 0,
+        })
+    }
+
+    /// Count the bits of every whole sample of `width` bytes in a buffer,
+    /// ignoring the bytes left over at the end
+    pub fn from_chunks(data: &[u8], width: usize) -> Result<BitBias> {
+        let mut bias = BitBias::new(width)?;
+        for chunk in data.chunks_exact(width) {
+            bias.count(chunk);
+        }
+        Ok(bias)
+    }
+
+    pub fn add(&mut self, sample: &[u8]) -> Result<()> {
+        if sample.len() * 8 != self.ones.len() {
+            return Err(Error::BadLength(format!(
+                "Expected a sample of {} bytes, got {}",
+                self.ones.len() / 8,
+                sample.len()
+            )));
+        }
+        self.count(sample);
+        Ok(())
+    }
+
+    fn count(&mut self, sample: &[u8]) {
+        for (i, ones) in self.ones.iter_mut().enumerate() {
+            if get_bit(sample, i) {
+                *ones += 1;
+            }
+        }
+        self.samples += 1;
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Share of the samples in which bit `i` is set,
+    /// or None before any sample was counted
+    pub fn frequency(&self, i: usize) -> Option<f64> {
+        if self.samples == 0 {
+            return None;
+        }
+        Some(self.ones[i] as f64 / self.samples as f64)
+    }
+
+    /// Distance of the frequency of bit `i` from 0.5, from -0.5 for a bit
+    /// never set to 0.5 for a bit always set
+    pub fn bias(&self, i: usize) -> Option<f64> {
+        self.frequency(i).map(|f| f - 0.5)
+    }
+
+    /// Number of standard deviations the count of bit `i` is away from
+    /// half the samples, the count of an unbiased bit being binomial.
+    /// Values beyond 4 or so are very unlikely for random data.
+    pub fn z_score(&self, i: usize) -> Option<f64> {
+        if self.samples == 0 {
+            return None;
+        }
+        let n = self.samples as f64;
+        Some((self.ones[i] as f64 - n / 2.0) / (n / 4.0).sqrt())
+    }
+
+    /// The bit whose z-score is furthest from 0, along with that z-score
+    pub fn most_biased(&self) -> Option<(usize, f64)> {
+        (0..self.ones.len())
+            .filter_map(|i| Some((i, self.z_score(i)?)))
+            .max_by(|a, b| {
+                a.1.abs()
+                    .partial_cmp(&b.1.abs())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+}
+
+#[test]
+fn test_weight_and_distance() {
+    use crate::random::{random_bytes, seeded_rng};
+    let mut rng = seeded_rng(23);
+    let x = random_bytes(&mut rng, 1001);
+    let y = random_bytes(&mut rng, 1001);
+    let naive: u32 = x.iter().zip(&y).map(|(a, b)| (a ^ b).count_ones()).sum();
+    assert_eq!(distance(&x, &y).unwrap(), naive as u64);
+    let naive: u32 = x.iter().map(|b| b.count_ones()).sum();
+    assert_eq!(weight(&x), naive as u64);
+    assert_eq!(distance(b"this is a test", b"wokka wokka!!!").unwrap(), 37);
+    assert!(distance(&x, &y[1..]).is_err());
+}
+
+#[test]
+fn test_differing_bits() {
+    let x = [0u8; 10];
+    let mut y = x;
+    for &i in [0, 7, 8, 63, 64, 79].iter() {
+        flip_bit(&mut y, i);
+    }
+    assert_eq!(differing_bits(&x, &y).unwrap(), [0, 7, 8, 63, 64, 79]);
+    assert_eq!(y[0], 0x81);
+    assert!(get_bit(&y, 79) && !get_bit(&y, 78));
+}
+
+#[cfg(test)]
+struct XorCipher([u8; 4]);
+
+#[cfg(test)]
+impl BlockCipher for XorCipher {
+    fn block_size(&self) -> usize {
+        4
+    }
+    fn encrypt_block(&self, block: &mut [u8]) {
+        block.iter_mut().zip(&self.0).for_each(|(b, k)| *b ^= k);
+    }
+    fn decrypt_block(&self, block: &mut [u8]) {
+        self.encrypt_block(block)
+    }
+}
+
+#[test]
+fn test_avalanche_without_diffusion() {
+    let mut rng = crate::random::seeded_rng(23);
+    let result = avalanche(&XorCipher(*b"key!"), &mut rng, 10).unwrap();
+    assert_eq!(result.mean(), 1.0 / 32.0);
+    assert_eq!(result.probability(5, 5), 1.0);
+    assert_eq!(result.probability(5, 6), 0.0);
+    assert_eq!(result.worst_deviation(), 0.5);
+    assert!(avalanche(&XorCipher(*b"key!"), &mut rng, 0).is_err());
+}
+
+#[test]
+fn test_bit_bias() {
+    use crate::random::{random_bytes, seeded_rng};
+    let random = random_bytes(&mut seeded_rng(23), 4096);
+    let bias = BitBias::from_chunks(&random, 4).unwrap();
+    assert_eq!(bias.samples(), 1024);
+    assert!(bias.most_biased().unwrap().1.abs() < 4.5);
+
+    // The top bit of ASCII text is never set
+    let text = b"Bits of ASCII text are far from random. ".repeat(10);
+    let bias = BitBias::from_chunks(&text, 1).unwrap();
+    assert_eq!(bias.bias(0), Some(-0.5));
+    assert_eq!(bias.most_biased().unwrap().0, 0);
+    assert!(BitBias::new(2).unwrap().add(b"abc").is_err());
+    let empty = BitBias::new(2).unwrap();
+    assert!(empty.most_biased().is_none());
+    assert!(empty.frequency(3).is_none());
+    assert!(empty.bias(3).is_none());
+    assert!(empty.z_score(3).is_none());
+    assert!(BitBias::new(0).is_err());
+    assert!(BitBias::from_chunks(&text, 0).is_err());
+}
@@ -0,0 +1,11 @@
+/// A keyed block cipher operating on fixed size blocks in place.
+/// Block cipher modes are written against this trait so they
+/// work with any block size.
+pub trait BlockCipher {
+    /// Block size in bytes
+    fn block_size(&self) -> usize;
+    /// Encrypt a single block in place
+    fn encrypt_block(&self, block: &mut [u8]);
+    /// Decrypt a single block in place
+    fn decrypt_block(&self, block: &mut [u8]);
+}
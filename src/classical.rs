@@ -0,0 +1,540 @@
+//! Classical ciphers over the 26 letters of the Latin alphabet: Caesar,
+//! Vigenère, affine and autokey, along with their breakers.
+//!
+//! Only ASCII letters are encrypted, keeping their case. Every other
+//! byte is copied as it is and does not use up key letters. Keys are
+//! made of letters, A or a standing for a shift of 0.
+//!
+//! The breakers rate candidate plaintexts with a `Scorer`, like the XOR
+//! breakers. Vigenère and autokey keys are recovered as in
+//! `repeating_xor`: the letters encrypted with the same key letter are
+//! gathered into a column and each column is solved as a Caesar cipher.
+use crate::error::{Error, Result};
+use crate::repeating_xor::{best_trial, confidence};
+use crate::scoring::{Candidate, LogLikelihood, Scorer};
+
+/// Number of letters in the alphabet
+const LETTERS: u8 = 26;
+
+/// Letters a trigram is made of, for the Kasiski examination
+const KASISKI_LENGTH: usize = 3;
+
+/// Apply `f` to the index in the alphabet of every ASCII letter
+/// of a text, keeping its case, and copy every other byte
+fn map_letters<F: FnMut(u8) -> u8>(text: &[u8], mut f: F) -> Vec<u8> {
+    text.iter()
+        .map(|&b| {
+            if b.is_ascii_uppercase() {
+                b'A' + f(b - b'A')
+            } else if b.is_ascii_lowercase() {
+                b'a' + f(b - b'a')
+            } else {
+                b
+            }
+        })
+        .collect()
+}
+
+/// Indices in the alphabet of the letters of a text, other bytes left out
+fn letter_indices(text: &[u8]) -> Vec<u8> {
+    text.iter()
+        .filter(|b| b.is_ascii_alphabetic())
+        .map(|b| b.to_ascii_uppercase() - b'A')
+        .collect()
+}
+
+/// Indices in the alphabet of the letters of a key,
+/// which must not be empty or hold anything but letters
+fn key_indices(key: &[u8]) -> Result<Vec<u8>> {
+    if key.is_empty() {
+        return Err(Error::BadLength("Key must not be empty".to_string()));
+    }
+    if let Some(offset) = key.iter().position(|b| !b.is_ascii_alphabetic()) {
+        return Err(Error::InvalidCharacter {
+            byte: key[offset],
+            offset,
+        });
+    }
+    Ok(letter_indices(key))
+}
+
+/// Uppercase letters of the given indices in the alphabet
+fn key_letters(indices: &[u8]) -> Vec<u8> {
+    indices.iter().map(|i| b'A' + i).collect()
+}
+
+/// Shift every letter `shift` places forward in the alphabet
+pub fn caesar_encrypt(text: &[u8], shift: u8) -> Vec<u8> {
+    let shift = shift % LETTERS;
+    map_letters(text, |x| (x + shift) % LETTERS)
+}
+
+/// Shift every letter `shift` places back in the alphabet
+pub fn caesar_decrypt(text: &[u8], shift: u8) -> Vec<u8> {
+    caesar_encrypt(text, LETTERS - shift % LETTERS)
+}
+
+/// Shift each letter by the next letter of the key, cycling through it
+pub fn vigenere_encrypt(text: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    let key = key_indices(key)?;
+    let mut position = 0;
+    Ok(map_letters(text, |x| {
+        let k = key[position % key.len()];
+        position += 1;
+        (x + k) % LETTERS
+    }))
+}
+
+pub fn vigenere_decrypt(text: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    let key = key_indices(key)?;
+    let mut position = 0;
+    Ok(map_letters(text, |x| {
+        let k = key[position % key.len()];
+        position += 1;
+        (x + LETTERS - k) % LETTERS
+    }))
+}
+
+/// Shift each letter by the next letter of the primer, then by
+/// the letters of the plaintext itself once the primer is used up
+pub fn autokey_encrypt(text: &[u8], primer: &[u8]) -> Result<Vec<u8>> {
+    let mut key = key_indices(primer)?;
+    let mut position = 0;
+    Ok(map_letters(text, |x| {
+        let k = key[position];
+        key.push(x);
+        position += 1;
+        (x + k) % LETTERS
+    }))
+}
+
+pub fn autokey_decrypt(text: &[u8], primer: &[u8]) -> Result<Vec<u8>> {
+    let mut key = key_indices(primer)?;
+    let mut position = 0;
+    Ok(map_letters(text, |y| {
+        let x = (y + LETTERS - key[position]) % LETTERS;
+        key.push(x);
+        position += 1;
+        x
+    }))
+}
+
+/// Key of the affine cipher, mapping letter `x` to `a * x + b`.
+/// `a` must be coprime with 26 for the mapping to be reversible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AffineKey {
+    a: u8,
+    b: u8,
+}
+
+impl AffineKey {
+    pub fn new(a: u8, b: u8) -> Result<AffineKey> {
+        let a = a % LETTERS;
+        if a.is_multiple_of(2) || a == 13 {
+            return Err(Error::Crypto(format!(
+                "Affine multiplier {} is not coprime with 26",
+                a
+            )));
+        }
+        Ok(AffineKey { a, b: b % LETTERS })
+    }
+
+    /// Every valid key, 12 multipliers times 26 shifts
+    pub fn all() -> impl Iterator<Item = AffineKey> {
+        (1..LETTERS)
+            .filter(|a| a % 2 == 1 && *a != 13)
+            .flat_map(|a| (0..LETTERS).map(move |b| AffineKey { a, b }))
+    }
+
+    pub fn a(&self) -> u8 {
+        self.a
+    }
+
+    pub fn b(&self) -> u8 {
+        self.b
+    }
+
+    /// Inverse of `a` modulo 26
+    fn a_inverse(&self) -> u8 {
+        (1..LETTERS)
+            .find(|i| (self.a as u32 * *i as u32) % LETTERS as u32 == 1)
+            .unwrap_or(1)
+    }
+
+    pub fn encrypt(&self, text: &[u8]) -> Vec<u8> {
+        map_letters(text, |x| {
+            ((self.a as u32 * x as u32 + self.b as u32) % LETTERS as u32) as u8
+        })
+    }
+
+    pub fn decrypt(&self, text: &[u8]) -> Vec<u8> {
+        let inverse = self.a_inverse() as u32;
+        map_letters(text, |y| {
+            ((inverse * (y + LETTERS - self.b) as u32) % LETTERS as u32) as u8
+        })
+    }
+}
+
+/// Key and plaintext recovered from a Vigenère or autokey ciphertext
+#[derive(Debug, Clone)]
+pub struct Solution {
+    /// The key, or the primer of an autokey cipher, in uppercase letters
+    pub key: Vec<u8>,
+    /// Confidence in each key letter, see `repeating_xor::Solution`
+    pub confidence: Vec<f32>,
+    pub plaintext: Vec<u8>,
+}
+
+/// Settings of the key length search of the Vigenère and autokey breakers
+#[derive(Debug, Clone)]
+pub struct KeyLengthOptions {
+    /// Shortest key tried
+    pub min_len: usize,
+    /// Longest key tried, capped at half the number of letters
+    pub max_len: usize,
+    /// Number of best key lengths of each statistic fully decrypted
+    /// by the Vigenère breaker. The autokey breaker tries every length.
+    pub trials: usize,
+}
+
+impl Default for KeyLengthOptions {
+    fn default() -> KeyLengthOptions {
+        KeyLengthOptions {
+            min_len: 1,
+            max_len: 20,
+            trials: 3,
+        }
+    }
+}
+
+/// Try every shift and return the candidate whose plaintext scores
+/// best, scored against English. The key of the candidate is the shift.
+pub fn break_caesar(ciphertext: &[u8]) -> Candidate {
+    break_caesar_with(ciphertext, &LogLikelihood::default())
+}
+
+/// Same as `break_caesar`, rating plaintexts with `scorer`
+pub fn break_caesar_with<S: Scorer + ?Sized>(ciphertext: &[u8], scorer: &S) -> Candidate {
+    (0..LETTERS)
+        .map(|shift| {
+            let mut candidate = Candidate::new(caesar_decrypt(ciphertext, shift));
+            candidate.key = shift;
+            candidate.get_score(scorer);
+            candidate
+        })
+        .fold(Candidate::new(Vec::new()), |best, candidate| {
+            if candidate.score < best.score {
+                candidate
+            } else {
+                best
+            }
+        })
+}
+
+/// Try all 312 affine keys, scored against English
+pub fn break_affine(ciphertext: &[u8]) -> (AffineKey, Candidate) {
+    break_affine_with(ciphertext, &LogLikelihood::default())
+}
+
+/// Same as `break_affine`, rating plaintexts with `scorer`
+pub fn break_affine_with<S: Scorer + ?Sized>(
+    ciphertext: &[u8],
+    scorer: &S,
+) -> (AffineKey, Candidate) {
+    let mut best = (AffineKey { a: 1, b: 0 }, Candidate::new(Vec::new()));
+    for key in AffineKey::all() {
+        let mut candidate = Candidate::new(key.decrypt(ciphertext));
+        candidate.get_score(scorer);
+        if candidate.score < best.1.score {
+            best = (key, candidate);
+        }
+    }
+    best
+}
+
+/// Largest key length the options allow for a number of letters
+fn max_key_len(letters: usize, options: &KeyLengthOptions) -> Result<usize> {
+    let max_len = options.max_len.min(letters / 2);
+    if options.min_len == 0 || options.min_len > max_len {
+        return Err(Error::BadLength(format!(
+            "No key length between {} and {} fits a ciphertext of {} letters",
+            options.min_len, options.max_len, letters
+        )));
+    }
+    Ok(max_len)
+}
+
+/// Average index of coincidence of the columns of letters for every key
+/// length in the range of the options, highest first. English comes
+/// close to 0.066 and uniformly random letters to 0.038.
+pub fn estimate_key_lengths(
+    ciphertext: &[u8],
+    options: &KeyLengthOptions,
+) -> Result<Vec<(usize, f64)>> {
+    let letters = letter_indices(ciphertext);
+    let max_len = max_key_len(letters.len(), options)?;
+    let mut lengths: Vec<(usize, f64)> = (options.min_len..=max_len)
+        .map(|len| {
+            let sum: f64 = (0..len)
+                .map(|column| {
+                    let mut counts = [0usize; LETTERS as usize];
+                    letters
+                        .iter()
+                        .skip(column)
+                        .step_by(len)
+                        .for_each(|&x| counts[x as usize] += 1);
+                    let n: usize = counts.iter().sum();
+                    let coincidences: usize = counts.iter().map(|&c| c * c.saturating_sub(1)).sum();
+                    coincidences as f64 / (n * (n - 1)) as f64
+                })
+                .sum();
+            (len, sum / len as f64)
+        })
+        .collect();
+    lengths.sort_by(|a, b| {
+        (b.1, a.0)
+            .partial_cmp(&(a.1, b.0))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    Ok(lengths)
+}
+
+/// Kasiski examination: the distances between repeated trigrams of the
+/// ciphertext are mostly multiples of the key length. Returns every key
+/// length in the range of the options along with the number of
+/// distances it divides, most first.
+pub fn kasiski(ciphertext: &[u8], options: &KeyLengthOptions) -> Result<Vec<(usize, usize)>> {
+    let letters = letter_indices(ciphertext);
+    let max_len = max_key_len(letters.len(), options)?;
+    let mut last_seen = std::collections::HashMap::new();
+    let mut distances = Vec::new();
+    for (i, trigram) in letters.windows(KASISKI_LENGTH).enumerate() {
+        if let Some(previous) = last_seen.insert(trigram, i) {
+            distances.push(i - previous);
+        }
+    }
+    let mut lengths: Vec<(usize, usize)> = (options.min_len..=max_len)
+        .map(|len| {
+            let count = distances.iter().filter(|d| d.is_multiple_of(len)).count();
+            (len, count)
+        })
+        .collect();
+    lengths.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    Ok(lengths)
+}
+
+/// Solve the columns of a key of length `len`. `decrypt_column` gives
+/// the plaintext letters of a column for a key letter. Returns the key
+/// letters and the confidence in each.
+fn solve_columns<S, F>(len: usize, scorer: &S, mut decrypt_column: F) -> (Vec<u8>, Vec<f32>)
+where
+    S: Scorer + ?Sized,
+    F: FnMut(usize, u8) -> Vec<u8>,
+{
+    let mut key = Vec::with_capacity(len);
+    let mut confidences = Vec::with_capacity(len);
+    for column in 0..len {
+        let mut scores: Vec<(f32, u8)> = (0..LETTERS)
+            .map(|k| {
+                let text: Vec<u8> = decrypt_column(column, k).iter().map(|x| b'a' + x).collect();
+                (scorer.score(&text), k)
+            })
+            .collect();
+        scores.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        key.push(scores[0].1);
+        confidences.push(confidence(scores[0].0, scores.get(1).map(|s| s.0)));
+    }
+    (key, confidences)
+}
+
+/// Recover the key and plaintext of a Vigenère ciphertext,
+/// scored against English
+pub fn break_vigenere(ciphertext: &[u8]) -> Result<Solution> {
+    break_vigenere_with(
+        ciphertext,
+        &LogLikelihood::default(),
+        &KeyLengthOptions::default(),
+    )
+}
+
+/// Same as `break_vigenere`, rating plaintexts with `scorer`. The best
+/// `options.trials` key lengths by index of coincidence and by Kasiski
+/// examination are each solved column by column, and the plaintext that
+/// scores best wins, shorter keys winning ties.
+pub fn break_vigenere_with<S: Scorer + ?Sized>(
+    ciphertext: &[u8],
+    scorer: &S,
+    options: &KeyLengthOptions,
+) -> Result<Solution> {
+    let letters = letter_indices(ciphertext);
+    let trials = options.trials.max(1);
+    let mut lengths: Vec<usize> = estimate_key_lengths(ciphertext, options)?
+        .iter()
+        .take(trials)
+        .map(|&(len, _)| len)
+        .collect();
+    for (len, _) in kasiski(ciphertext, options)?.into_iter().take(trials) {
+        if !lengths.contains(&len) {
+            lengths.push(len);
+        }
+    }
+    let solutions = lengths
+        .into_iter()
+        .map(|len| {
+            let (key, confidence) = solve_columns(len, scorer, |column, k| {
+                letters
+                    .iter()
+                    .skip(column)
+                    .step_by(len)
+                    .map(|y| (y + LETTERS - k) % LETTERS)
+                    .collect()
+            });
+            let key = key_letters(&key);
+            let plaintext = vigenere_decrypt(ciphertext, &key)?;
+            let solution = Solution {
+                key,
+                confidence,
+                plaintext,
+            };
+            Ok((scorer.score(&solution.plaintext), solution))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    best_trial(solutions, |solution: &Solution| solution.key.len())
+        .ok_or_else(|| Error::AttackFailed("No key length to try".to_string()))
+}
+
+/// Recover the primer and plaintext of an autokey ciphertext,
+/// scored against English
+pub fn break_autokey(ciphertext: &[u8]) -> Result<Solution> {
+    break_autokey_with(
+        ciphertext,
+        &LogLikelihood::default(),
+        &KeyLengthOptions::default(),
+    )
+}
+
+/// Same as `break_autokey`, rating plaintexts with `scorer`. For a primer
+/// of length `n`, plaintext letter `i` is the key of letter `i + n`, so
+/// the letters `n` apart form a chain decrypted from a single primer
+/// letter, solved like a column of a Vigenère ciphertext. Every primer
+/// length in the range of the options is tried.
+pub fn break_autokey_with<S: Scorer + ?Sized>(
+    ciphertext: &[u8],
+    scorer: &S,
+    options: &KeyLengthOptions,
+) -> Result<Solution> {
+    let letters = letter_indices(ciphertext);
+    let max_len = max_key_len(letters.len(), options)?;
+    let solutions = (options.min_len..=max_len)
+        .map(|len| {
+            let (key, confidence) = solve_columns(len, scorer, |column, k| {
+                let mut key = k;
+                letters
+                    .iter()
+                    .skip(column)
+                    .step_by(len)
+                    .map(|y| {
+                        key = (y + LETTERS - key) % LETTERS;
+                        key
+                    })
+                    .collect()
+            });
+            let key = key_letters(&key);
+            let plaintext = autokey_decrypt(ciphertext, &key)?;
+            let solution = Solution {
+                key,
+                confidence,
+                plaintext,
+            };
+            Ok((scorer.score(&solution.plaintext), solution))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    best_trial(solutions, |solution: &Solution| solution.key.len())
+        .ok_or_else(|| Error::AttackFailed("No key length to try".to_string()))
+}
+
+#[cfg(test)]
+const CLASSICAL_PLAINTEXT: &[u8] = b"It is a truth universally acknowledged, that a \
+    single man in possession of a good fortune, must be in want of a wife. However \
+    little known the feelings or views of such a man may be on his first entering a \
+    neighbourhood, this truth is so well fixed in the minds of the surrounding \
+    families, that he is considered the rightful property of some one or other of \
+    their daughters. My dear Mr. Bennet, said his lady to him one day, have you \
+    heard that Netherfield Park is let at last? Mr. Bennet replied that he had not. \
+    But it is, returned she; for Mrs. Long has just been here, and she told me all \
+    about it.";
+
+#[test]
+fn test_classical_vectors() {
+    assert_eq!(caesar_encrypt(b"Hello, World!", 3), b"Khoor, Zruog!");
+    assert_eq!(caesar_decrypt(b"Khoor, Zruog!", 29), b"Hello, World!");
+    assert_eq!(
+        vigenere_encrypt(b"ATTACK AT DAWN", b"lemon").unwrap(),
+        b"LXFOPV EF RNHR"
+    );
+    assert_eq!(
+        vigenere_decrypt(b"LXFOPV EF RNHR", b"LEMON").unwrap(),
+        b"ATTACK AT DAWN"
+    );
+    assert_eq!(
+        autokey_encrypt(b"attack at dawn", b"QUEENLY").unwrap(),
+        b"qnxepv yt wtwp"
+    );
+    assert_eq!(
+        autokey_decrypt(b"qnxepv yt wtwp", b"QUEENLY").unwrap(),
+        b"attack at dawn"
+    );
+    let key = AffineKey::new(5, 8).unwrap();
+    assert_eq!(key.encrypt(b"AFFINE cipher"), b"IHHWVC swfrcp");
+    assert_eq!(key.decrypt(b"IHHWVC swfrcp"), b"AFFINE cipher");
+    assert_eq!(AffineKey::all().count(), 312);
+
+    assert!(AffineKey::new(13, 1).is_err());
+    assert!(vigenere_encrypt(b"text", b"").is_err());
+    assert!(matches!(
+        autokey_encrypt(b"text", b"ke y"),
+        Err(Error::InvalidCharacter {
+            byte: b' ',
+            offset: 2
+        })
+    ));
+}
+
+#[test]
+fn test_break_caesar_and_affine() {
+    let ciphertext = caesar_encrypt(CLASSICAL_PLAINTEXT, 11);
+    let candidate = break_caesar(&ciphertext);
+    assert_eq!(candidate.key, 11);
+    assert_eq!(candidate.plaintext, CLASSICAL_PLAINTEXT);
+
+    let key = AffineKey::new(17, 20).unwrap();
+    let (recovered, candidate) = break_affine(&key.encrypt(CLASSICAL_PLAINTEXT));
+    assert_eq!(recovered, key);
+    assert_eq!(candidate.plaintext, CLASSICAL_PLAINTEXT);
+}
+
+#[test]
+fn test_break_vigenere() {
+    let ciphertext = vigenere_encrypt(CLASSICAL_PLAINTEXT, b"Scytale").unwrap();
+    let options = KeyLengthOptions::default();
+    let lengths = estimate_key_lengths(&ciphertext, &options).unwrap();
+    assert!(lengths[..options.trials].iter().any(|&(len, _)| len == 7));
+    assert!(kasiski(&ciphertext, &options)
+        .unwrap()
+        .iter()
+        .take(options.trials)
+        .any(|&(len, _)| len == 7));
+    let solution = break_vigenere(&ciphertext).unwrap();
+    assert_eq!(solution.key, b"SCYTALE");
+    assert_eq!(solution.plaintext, CLASSICAL_PLAINTEXT);
+    assert!(break_vigenere(b"x, ").is_err());
+}
+
+#[test]
+fn test_break_autokey() {
+    let ciphertext = autokey_encrypt(CLASSICAL_PLAINTEXT, b"PRIMER").unwrap();
+    let solution = break_autokey(&ciphertext).unwrap();
+    assert_eq!(solution.key, b"PRIMER");
+    assert_eq!(solution.confidence.len(), 6);
+    assert_eq!(solution.plaintext, CLASSICAL_PLAINTEXT);
+}
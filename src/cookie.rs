@@ -0,0 +1,77 @@
+//! Structured cookies of challenge 13: `k=v` user profiles
+//! encrypted with AES-128 in ECB mode.
+use crate::error::{Error, Result};
+use crate::modes::{aes_128, decrypt_aes_128_ecb, encrypt_ecb, BLOCK_SIZE};
+use crate::padding::{pkcs7, Padding};
+use std::collections::HashMap;
+use url::Url;
+
+/// Parse parameters from URL
+pub fn parse(parameters: &str) -> Result<HashMap<String, String>> {
+    let mut url: String = "https://base.com/?".to_owned();
+    url.push_str(parameters);
+    let parsed_url = Url::parse(&url[..]).map_err(|e| Error::InvalidEncoding(e.to_string()))?;
+    let output_object: HashMap<String, String> = parsed_url.query_pairs().into_owned().collect();
+    Ok(output_object)
+}
+
+/// Create profile for a given email address
+pub fn profile_for(email_address: &str) -> String {
+    let email_address = &email_address.replace("&", "");
+    let email_address = &email_address.replace("=", "");
+    let mut result: String = "email=".to_owned();
+    result += email_address;
+    result += "&uid=10&role=user";
+    result
+}
+
+pub fn encrypt_profile(email_address: &str, key: &[u8]) -> Result<Vec<u8>> {
+    let profile = profile_for(email_address);
+    encrypt_ecb(&aes_128(key)?, &pkcs7(profile.as_bytes(), BLOCK_SIZE)?)
+}
+
+pub fn decrypt_and_parse_profile(ciphertext: &[u8], key: &[u8]) -> Result<HashMap<String, String>> {
+    let plaintext = decrypt_aes_128_ecb(ciphertext, key, Padding::Pkcs7)?;
+    let plaintext = String::from_utf8(plaintext)?;
+    let profile = parse(&plaintext)?;
+    Ok(profile)
+}
+
+#[test]
+fn test_url_parsing() -> Result<()> {
+    let test_object = parse("foo=bar&baz=qux&zap=zazzle")?;
+    assert_eq!(test_object.get("foo").unwrap().to_owned(), "bar".to_owned());
+    assert_eq!(test_object.get("baz").unwrap().to_owned(), "qux".to_owned());
+    assert_eq!(
+        test_object.get("zap").unwrap().to_owned(),
+        "zazzle".to_owned()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_profile_for() {
+    let test_object = profile_for("foo@bar.com");
+    assert_eq!(test_object, "email=foo@bar.com&uid=10&role=user".to_owned());
+    let test_object = profile_for("foo@bar.com&role=admin");
+    assert_eq!(
+        test_object,
+        "email=foo@bar.comroleadmin&uid=10&role=user".to_owned()
+    );
+}
+
+#[test]
+fn test_encrypt_decrypt_profile() -> Result<()> {
+    let key = &[0; 16];
+    let test_object = decrypt_and_parse_profile(&encrypt_profile("foo@bar.com", key)?, key)?;
+    assert_eq!(
+        test_object.get("email").unwrap().to_owned(),
+        "foo@bar.com".to_owned()
+    );
+    assert_eq!(test_object.get("uid").unwrap().to_owned(), "10".to_owned());
+    assert_eq!(
+        test_object.get("role").unwrap().to_owned(),
+        "user".to_owned()
+    );
+    Ok(())
+}
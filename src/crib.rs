@@ -0,0 +1,280 @@
+//! Crib dragging over ciphertexts encrypted with the same keystream.
+//!
+//! XORing two such ciphertexts cancels the keystream and leaves the XOR
+//! of their plaintexts. Sliding a guessed word, the crib, across it
+//! reveals a fragment of the other plaintext at every offset, which
+//! reads as text where the guess is right. Right guesses are locked in,
+//! revealing keystream bytes and with them every other ciphertext.
+use crate::error::{Error, Result};
+use crate::many_time_pad;
+use crate::scoring::{Candidate, Scorer};
+
+/// What a crib reveals at an offset
+#[derive(Debug, Clone)]
+pub struct CribMatch {
+    pub offset: usize,
+    /// The bytes revealed in each ciphertext, see `CribSession::drag`
+    pub fragments: Vec<Vec<u8>>,
+    /// Everything the crib revealed, scored
+    pub candidate: Candidate,
+    /// Whether every revealed byte is printable
+    pub printable: bool,
+}
+
+/// Printable ASCII along with the usual whitespace
+pub fn is_printable(byte: u8) -> bool {
+    byte.is_ascii_graphic() || matches!(byte, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+/// XOR two ciphertexts over the length of the shorter one
+pub fn xor_ciphertexts(x: &[u8], y: &[u8]) -> Vec<u8> {
+    x.iter().zip(y).map(|(a, b)| a ^ b).collect()
+}
+
+/// Slide a crib across the XOR of two ciphertexts, returning what it
+/// reveals of the other plaintext at every offset, in offset order
+pub fn drag<S: Scorer + ?Sized>(xored: &[u8], crib: &[u8], scorer: &S) -> Vec<CribMatch> {
+    if crib.is_empty() || crib.len() > xored.len() {
+        return Vec::new();
+    }
+    (0..=xored.len() - crib.len())
+        .map(|offset| {
+            let fragment = xor_ciphertexts(&xored[offset..], crib);
+            scored_match(offset, vec![fragment.clone()], fragment, scorer)
+        })
+        .collect()
+}
+
+fn scored_match<S: Scorer + ?Sized>(
+    offset: usize,
+    fragments: Vec<Vec<u8>>,
+    revealed: Vec<u8>,
+    scorer: &S,
+) -> CribMatch {
+    let printable = revealed.iter().all(|&b| is_printable(b));
+    let mut candidate = Candidate::new(revealed);
+    candidate.get_score(scorer);
+    CribMatch {
+        offset,
+        fragments,
+        candidate,
+        printable,
+    }
+}
+
+/// A set of ciphertexts sharing a keystream, and the keystream bytes
+/// recovered so far
+#[derive(Debug, Clone)]
+pub struct CribSession {
+    ciphertexts: Vec<Vec<u8>>,
+    keystream: Vec<Option<u8>>,
+}
+
+impl CribSession {
+    pub fn new(ciphertexts: Vec<Vec<u8>>) -> Result<CribSession> {
+        if ciphertexts.len() < 2 {
+            return Err(Error::AttackFailed(
+                "Crib dragging needs at least two ciphertexts".to_string(),
+            ));
+        }
+        let len = ciphertexts.iter().map(|c| c.len()).max().unwrap_or(0);
+        Ok(CribSession {
+            ciphertexts,
+            keystream: vec![None; len],
+        })
+    }
+
+    pub fn ciphertexts(&self) -> &[Vec<u8>] {
+        &self.ciphertexts
+    }
+
+    /// The keystream, with the bytes not recovered yet left out
+    pub fn keystream(&self) -> &[Option<u8>] {
+        &self.keystream
+    }
+
+    /// Check that a ciphertext exists and is long enough for
+    /// `len` bytes of plaintext at `offset`
+    fn check_range(&self, index: usize, offset: usize, len: usize) -> Result<()> {
+        let ciphertext = self.ciphertexts.get(index).ok_or_else(|| {
+            Error::BadLength(format!(
+                "No ciphertext {}, there are {}",
+                index,
+                self.ciphertexts.len()
+            ))
+        })?;
+        if offset + len > ciphertext.len() {
+            return Err(Error::BadLength(format!(
+                "{} bytes at offset {} do not fit ciphertext {} of {} bytes",
+                len,
+                offset,
+                index,
+                ciphertext.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Slide a crib across ciphertext `index`. At every offset, the crib
+    /// gives keystream bytes that decrypt the same bytes of the other
+    /// ciphertexts. The fragments of a match hold those bytes for every
+    /// ciphertext, the crib itself for ciphertext `index`, and the
+    /// candidate holds the bytes of the other ciphertexts together.
+    pub fn drag<S: Scorer + ?Sized>(
+        &self,
+        index: usize,
+        crib: &[u8],
+        scorer: &S,
+    ) -> Result<Vec<CribMatch>> {
+        self.check_range(index, 0, crib.len())?;
+        if crib.is_empty() {
+            return Ok(Vec::new());
+        }
+        let dragged = &self.ciphertexts[index];
+        let matches = (0..=dragged.len() - crib.len())
+            .map(|offset| {
+                let keystream = xor_ciphertexts(&dragged[offset..], crib);
+                let mut revealed = Vec::new();
+                let fragments = self
+                    .ciphertexts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ciphertext)| {
+                        if i == index {
+                            return crib.to_vec();
+                        }
+                        let fragment =
+                            xor_ciphertexts(ciphertext.get(offset..).unwrap_or(&[]), &keystream);
+                        revealed.extend_from_slice(&fragment);
+                        fragment
+                    })
+                    .collect();
+                scored_match(offset, fragments, revealed, scorer)
+            })
+            .collect();
+        Ok(matches)
+    }
+
+    /// Lock in a guess of the plaintext of ciphertext `index` at `offset`,
+    /// replacing any keystream bytes recovered there before
+    pub fn lock(&mut self, index: usize, offset: usize, plaintext: &[u8]) -> Result<()> {
+        self.check_range(index, offset, plaintext.len())?;
+        let ciphertext = &self.ciphertexts[index][offset..];
+        for (i, (c, p)) in ciphertext.iter().zip(plaintext).enumerate() {
+            self.keystream[offset + i] = Some(c ^ p);
+        }
+        Ok(())
+    }
+
+    /// Forget the keystream bytes recovered in a range
+    pub fn unlock(&mut self, offset: usize, len: usize) {
+        let end = (offset + len).min(self.keystream.len());
+        let start = offset.min(end);
+        self.keystream[start..end]
+            .iter_mut()
+            .for_each(|k| *k = None);
+    }
+
+    /// Lock in the keystream bytes of a statistical solution
+    /// recovered with at least `min_confidence`
+    pub fn seed(&mut self, solution: &many_time_pad::Solution, min_confidence: f32) {
+        for (i, (&key, &confidence)) in solution
+            .keystream
+            .iter()
+            .zip(&solution.confidence)
+            .enumerate()
+        {
+            if confidence >= min_confidence && i < self.keystream.len() {
+                self.keystream[i] = Some(key);
+            }
+        }
+    }
+
+    /// The plaintext of ciphertext `index` as far as it is revealed
+    pub fn plaintext(&self, index: usize) -> Result<Vec<Option<u8>>> {
+        self.check_range(index, 0, 0)?;
+        Ok(self.ciphertexts[index]
+            .iter()
+            .zip(&self.keystream)
+            .map(|(c, k)| k.map(|k| c ^ k))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+const CRIB_PLAINTEXTS: [&[u8]; 3] = [
+    b"Send the reinforcements to the northern gate at dawn",
+    b"Hold the bridge until the second company arrives",
+    b"The supply convoy leaves the harbour tomorrow night",
+];
+
+#[cfg(test)]
+fn crib_ciphertexts() -> Vec<Vec<u8>> {
+    let keystream = crate::random::random_bytes(&mut crate::random::seeded_rng(20), 64);
+    CRIB_PLAINTEXTS
+        .iter()
+        .map(|p| xor_ciphertexts(p, &keystream))
+        .collect()
+}
+
+#[test]
+fn test_drag_pair() {
+    let ciphertexts = crib_ciphertexts();
+    let xored = xor_ciphertexts(&ciphertexts[0], &ciphertexts[1]);
+    let scorer = crate::scoring::LogLikelihood::default();
+    let matches = drag(&xored, b" the ", &scorer);
+    assert_eq!(matches.len(), xored.len() - 4);
+    // " the " is at offset 4 of both plaintexts
+    assert_eq!(matches[4].fragments[0], b" the ");
+    assert!(matches[4].printable);
+    assert!(drag(&xored, b"", &scorer).is_empty());
+}
+
+#[test]
+fn test_session() {
+    let ciphertexts = crib_ciphertexts();
+    let scorer = crate::scoring::LogLikelihood::default();
+    let mut session = CribSession::new(ciphertexts.clone()).unwrap();
+    let mut matches: Vec<CribMatch> = session
+        .drag(1, b" the ", &scorer)
+        .unwrap()
+        .into_iter()
+        .filter(|m| m.printable)
+        .collect();
+    matches.sort_by(|a, b| a.candidate.score.partial_cmp(&b.candidate.score).unwrap());
+    // " the " is twice in the second plaintext
+    let mut offsets = [matches[0].offset, matches[1].offset];
+    offsets.sort_unstable();
+    assert_eq!(offsets, [4, 21]);
+    let at_4 = matches.iter().find(|m| m.offset == 4).unwrap();
+    assert_eq!(at_4.fragments, [&b" the "[..], b" the ", b"suppl"]);
+
+    session.lock(1, 4, b" the ").unwrap();
+    let revealed: Vec<u8> = session.plaintext(2).unwrap()[4..9]
+        .iter()
+        .flatten()
+        .copied()
+        .collect();
+    assert_eq!(revealed, b"suppl");
+    assert_eq!(session.plaintext(0).unwrap()[3], None);
+    assert!(session.plaintext(3).is_err());
+    session.unlock(5, 2);
+    assert_eq!(session.keystream()[4..9].iter().flatten().count(), 3);
+
+    assert!(session.lock(0, 50, b"too long").is_err());
+    assert!(session.lock(3, 0, b"x").is_err());
+    assert!(CribSession::new(vec![ciphertexts[0].clone()]).is_err());
+}
+
+#[test]
+fn test_seed_from_solution() {
+    let ciphertexts = crib_ciphertexts();
+    let solution = many_time_pad::Solution {
+        keystream: vec![1, 2, 3],
+        confidence: vec![0.9, 0.1, 0.8],
+        plaintexts: Vec::new(),
+    };
+    let mut session = CribSession::new(ciphertexts).unwrap();
+    session.seed(&solution, 0.5);
+    assert_eq!(session.keystream()[..3], [Some(1), None, Some(3)]);
+}
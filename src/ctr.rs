@@ -0,0 +1,287 @@
+//! Counter (CTR) mode, turning a block cipher into a stream cipher.
+//!
+//! The keystream is the encryption of successive counter blocks, each
+//! made of a fixed nonce followed by a counter. Encryption and
+//! decryption are the same operation, messages need no padding, and
+//! any part of the keystream can be generated without the parts before
+//! it, so the stream can be seeked.
+use crate::aes::Aes;
+use crate::cipher::BlockCipher;
+use crate::error::{Error, Result};
+
+/// Byte order of the counter within the counter block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+/// Where the counter sits in the counter block. The counter takes the
+/// last `counter_len` bytes of the block and the nonce the bytes before
+/// it. The counter wraps around to 0 after its largest value, without
+/// carrying into the nonce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterLayout {
+    /// Width of the counter in bytes, at most 16
+    pub counter_len: usize,
+    pub endianness: Endianness,
+}
+
+impl CounterLayout {
+    /// 64-bit nonce followed by a 64-bit little-endian counter,
+    /// as used by the cryptopals challenges
+    pub const CRYPTOPALS: CounterLayout = CounterLayout {
+        counter_len: 8,
+        endianness: Endianness::Little,
+    };
+
+    /// The whole 16 byte block taken as a big-endian counter, as in the
+    /// examples of NIST SP 800-38A
+    pub const FULL_BLOCK: CounterLayout = CounterLayout {
+        counter_len: 16,
+        endianness: Endianness::Big,
+    };
+
+    /// Largest counter value, after which the counter wraps around
+    fn max_counter(&self) -> u128 {
+        match self.counter_len {
+            16 => u128::MAX,
+            n => (1 << (8 * n)) - 1,
+        }
+    }
+}
+
+/// CTR mode keystream over any block cipher, keeping track of its
+/// position so a message can be processed in pieces
+pub struct Ctr<C: BlockCipher> {
+    cipher: C,
+    nonce: Vec<u8>,
+    initial_counter: u128,
+    layout: CounterLayout,
+    position: u64,
+}
+
+impl<C: BlockCipher> Ctr<C> {
+    /// Start the counter at `initial_counter`. The nonce must fill the
+    /// bytes of the block left by the counter.
+    pub fn new(
+        cipher: C,
+        nonce: &[u8],
+        initial_counter: u128,
+        layout: CounterLayout,
+    ) -> Result<Ctr<C>> {
+        let block_size = cipher.block_size();
+        if layout.counter_len == 0 || layout.counter_len > block_size.min(16) {
+            return Err(Error::BadLength(format!(
+                "Counter of {} bytes does not fit a block of {} bytes",
+                layout.counter_len, block_size
+            )));
+        }
+        if nonce.len() + layout.counter_len != block_size {
+            return Err(Error::BadLength(format!(
+                "Nonce of {} bytes and counter of {} bytes do not make a block of {} bytes",
+                nonce.len(),
+                layout.counter_len,
+                block_size
+            )));
+        }
+        if initial_counter > layout.max_counter() {
+            return Err(Error::BadLength(format!(
+                "Initial counter {} does not fit in {} bytes",
+                initial_counter, layout.counter_len
+            )));
+        }
+        Ok(Ctr {
+            cipher,
+            nonce: nonce.to_vec(),
+            initial_counter,
+            layout,
+            position: 0,
+        })
+    }
+
+    /// Start from a whole initial counter block, split into
+    /// nonce and counter according to the layout
+    pub fn from_counter_block(cipher: C, block: &[u8], layout: CounterLayout) -> Result<Ctr<C>> {
+        if layout.counter_len == 0 || layout.counter_len > block.len().min(16) {
+            return Err(Error::BadLength(format!(
+                "Counter of {} bytes does not fit a block of {} bytes",
+                layout.counter_len,
+                block.len()
+            )));
+        }
+        let (nonce, counter) = block.split_at(block.len() - layout.counter_len);
+        let mut bytes = [0; 16];
+        let initial_counter = match layout.endianness {
+            Endianness::Big => {
+                bytes[16 - counter.len()..].copy_from_slice(counter);
+                u128::from_be_bytes(bytes)
+            }
+            Endianness::Little => {
+                bytes[..counter.len()].copy_from_slice(counter);
+                u128::from_le_bytes(bytes)
+            }
+        };
+        Ctr::new(cipher, nonce, initial_counter, layout)
+    }
+
+    /// Offset in the keystream of the next byte to be used
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Move to an offset in the keystream
+    pub fn seek(&mut self, position: u64) {
+        self.position = position;
+    }
+
+    /// The counter block of keystream block `index`
+    pub fn counter_block(&self, index: u64) -> Vec<u8> {
+        let max = self.layout.max_counter();
+        let offset = index as u128 & max;
+        let counter = if offset > max - self.initial_counter {
+            offset - (max - self.initial_counter) - 1
+        } else {
+            self.initial_counter + offset
+        };
+        let mut block = self.nonce.clone();
+        let len = self.layout.counter_len;
+        match self.layout.endianness {
+            Endianness::Big => block.extend_from_slice(&counter.to_be_bytes()[16 - len..]),
+            Endianness::Little => block.extend_from_slice(&counter.to_le_bytes()[..len]),
+        }
+        block
+    }
+
+    /// XOR the keystream at the current position into a buffer,
+    /// encrypting or decrypting it, and move past it
+    pub fn apply(&mut self, buffer: &mut [u8]) {
+        let block_size = self.cipher.block_size() as u64;
+        let mut done = 0;
+        while done < buffer.len() {
+            let index = self.position / block_size;
+            let skip = (self.position % block_size) as usize;
+            let mut keystream = self.counter_block(index);
+            self.cipher.encrypt_block(&mut keystream);
+            let n = (keystream.len() - skip).min(buffer.len() - done);
+            buffer[done..done + n]
+                .iter_mut()
+                .zip(&keystream[skip..skip + n])
+                .for_each(|(b, k)| *b ^= k);
+            done += n;
+            self.position += n as u64;
+        }
+    }
+
+    /// The next `len` bytes of keystream
+    pub fn keystream(&mut self, len: usize) -> Vec<u8> {
+        let mut keystream = vec![0; len];
+        self.apply(&mut keystream);
+        keystream
+    }
+}
+
+/// Encrypt or decrypt a byte slice with AES in CTR mode, starting at
+/// counter 0. The key is 16, 24 or 32 bytes long.
+pub fn aes_ctr(input: &[u8], key: &[u8], nonce: &[u8], layout: CounterLayout) -> Result<Vec<u8>> {
+    let mut ctr = Ctr::new(Aes::new(key)?, nonce, 0, layout)?;
+    let mut output = input.to_vec();
+    ctr.apply(&mut output);
+    Ok(output)
+}
+
+#[cfg(test)]
+const SP_800_38A_PLAINTEXT: &str =
+    "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+    30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+#[test]
+fn test_sp_800_38a_vectors() {
+    use crate::encoding::{hex_decode, hex_encode};
+    // NIST SP 800-38A, F.5.1, F.5.3 and F.5.5
+    let vectors = [
+        (
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+             5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee",
+        ),
+        (
+            "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+            "1abc932417521ca24f2b0459fe7e6e0b090339ec0aa6faefd5ccc2c6f4ce8e94\
+             1e36b26bd1ebc670d1bd1d665620abf74f78a7f6d29809585a97daec58c6b050",
+        ),
+        (
+            "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+            "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5\
+             2b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6",
+        ),
+    ];
+    let counter_block = hex_decode("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff").unwrap();
+    let plaintext = hex_decode(SP_800_38A_PLAINTEXT).unwrap();
+    for (key, ciphertext) in vectors.iter() {
+        let aes = Aes::new(&hex_decode(key).unwrap()).unwrap();
+        let mut ctr =
+            Ctr::from_counter_block(aes, &counter_block, CounterLayout::FULL_BLOCK).unwrap();
+        let mut data = plaintext.clone();
+        ctr.apply(&mut data);
+        assert_eq!(hex_encode(&data), *ciphertext);
+        ctr.seek(0);
+        ctr.apply(&mut data);
+        assert_eq!(data, plaintext);
+    }
+}
+
+#[test]
+fn test_cryptopals_layout() {
+    // Challenge 18
+    let ciphertext = crate::encoding::base64_decode(
+        b"L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==",
+        crate::encoding::STANDARD,
+    )
+    .unwrap();
+    let plaintext = aes_ctr(
+        &ciphertext,
+        b"YELLOW SUBMARINE",
+        &[0; 8],
+        CounterLayout::CRYPTOPALS,
+    )
+    .unwrap();
+    assert_eq!(
+        plaintext,
+        &b"Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby "[..]
+    );
+}
+
+#[test]
+fn test_seek_and_counter_layout() {
+    let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+    let mut ctr = Ctr::new(aes.clone(), b"nonce!!!", 0, CounterLayout::CRYPTOPALS).unwrap();
+    let keystream = ctr.keystream(100);
+    assert_eq!(ctr.position(), 100);
+    ctr.seek(37);
+    assert_eq!(ctr.keystream(50), keystream[37..87]);
+    let mut pieces = Vec::new();
+    ctr.seek(0);
+    for len in [5, 16, 11, 68].iter() {
+        pieces.extend(ctr.keystream(*len));
+    }
+    assert_eq!(pieces, keystream);
+
+    assert_eq!(ctr.counter_block(258)[8..], [2, 1, 0, 0, 0, 0, 0, 0]);
+    let layout = CounterLayout {
+        counter_len: 2,
+        endianness: Endianness::Big,
+    };
+    let ctr = Ctr::new(aes.clone(), &[7; 14], 0xfffe, layout).unwrap();
+    assert_eq!(ctr.counter_block(1)[14..], [0xff, 0xff]);
+    // The counter wraps around without touching the nonce
+    assert_eq!(ctr.counter_block(3)[13..], [7, 0, 1]);
+
+    assert!(Ctr::new(aes.clone(), &[0; 7], 0, CounterLayout::CRYPTOPALS).is_err());
+    assert!(Ctr::new(aes.clone(), &[0; 14], 0x10000, layout).is_err());
+    let layout = CounterLayout {
+        counter_len: 0,
+        endianness: Endianness::Big,
+    };
+    assert!(Ctr::new(aes, &[0; 16], 0, layout).is_err());
+}
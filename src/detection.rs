@@ -0,0 +1,37 @@
+use crate::error::{Error, Result};
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// Check if an iter type has unique elements
+pub fn has_unique_elements<T>(iter: T) -> bool
+where
+    T: IntoIterator,
+    T::Item: Eq + Hash,
+{
+    let mut uniq = HashSet::new();
+    iter.into_iter().all(move |x| uniq.insert(x))
+}
+
+/// Check whether a given byte slice has repeated
+/// blocks of size blocksize
+pub fn repeated_blocks(ciphertext: &[u8], blocksize: usize) -> Result<bool> {
+    if blocksize == 0 || !ciphertext.len().is_multiple_of(blocksize) {
+        return Err(Error::BadLength(
+            "The length of the ciphertext must be a multiple of the blocksize".to_string(),
+        ));
+    }
+    Ok(!has_unique_elements(ciphertext.chunks(blocksize)))
+}
+
+#[test]
+fn test_has_unique_elements() {
+    assert!(has_unique_elements(vec![1, 2, 3]));
+    assert!(!has_unique_elements(vec![1, 2, 1]));
+}
+
+#[test]
+fn test_repeated_blocks() {
+    assert!(repeated_blocks(b"abcdabcd", 4).unwrap());
+    assert!(!repeated_blocks(b"abcdabce", 4).unwrap());
+    assert!(repeated_blocks(b"abcdabc", 4).is_err());
+}
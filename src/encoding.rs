@@ -0,0 +1,475 @@
+//! Hex and base64 codecs.
+//!
+//! Decoders skip ASCII whitespace, so line wrapped input is accepted,
+//! and report the byte offset of the first invalid character.
+use crate::error::{Error, Result};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+const HEX_LOWER: &[u8; 16] = b"0123456789abcdef";
+const HEX_UPPER: &[u8; 16] = b"0123456789ABCDEF";
+const BASE64_STANDARD: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_URL_SAFE: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Size of the chunks read by `DecodeReader`
+const CHUNK_SIZE: usize = 8192;
+
+/// Base64 alphabet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alphabet {
+    /// RFC 4648 standard alphabet, using `+` and `/`
+    Standard,
+    /// RFC 4648 URL and filename safe alphabet, using `-` and `_`
+    UrlSafe,
+}
+
+impl Alphabet {
+    fn symbols(self) -> &'static [u8; 64] {
+        match self {
+            Alphabet::Standard => BASE64_STANDARD,
+            Alphabet::UrlSafe => BASE64_URL_SAFE,
+        }
+    }
+
+    fn value(self, c: u8) -> Option<u8> {
+        match c {
+            b'A'..=b'Z' => Some(c - b'A'),
+            b'a'..=b'z' => Some(c - b'a' + 26),
+            b'0'..=b'9' => Some(c - b'0' + 52),
+            b'+' if self == Alphabet::Standard => Some(62),
+            b'/' if self == Alphabet::Standard => Some(63),
+            b'-' if self == Alphabet::UrlSafe => Some(62),
+            b'_' if self == Alphabet::UrlSafe => Some(63),
+            _ => None,
+        }
+    }
+}
+
+/// Base64 flavour: alphabet and whether output is padded with `=`.
+/// Decoding accepts both padded and unpadded input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Base64Config {
+    pub alphabet: Alphabet,
+    pub padding: bool,
+}
+
+pub const STANDARD: Base64Config = Base64Config {
+    alphabet: Alphabet::Standard,
+    padding: true,
+};
+pub const STANDARD_NO_PAD: Base64Config = Base64Config {
+    alphabet: Alphabet::Standard,
+    padding: false,
+};
+pub const URL_SAFE: Base64Config = Base64Config {
+    alphabet: Alphabet::UrlSafe,
+    padding: true,
+};
+pub const URL_SAFE_NO_PAD: Base64Config = Base64Config {
+    alphabet: Alphabet::UrlSafe,
+    padding: false,
+};
+
+/// Incremental decoder fed one character at a time
+pub trait Decoder {
+    /// Decode the character found at `offset` in the input,
+    /// appending any completed bytes to `output`
+    fn push(&mut self, c: u8, offset: usize, output: &mut Vec<u8>) -> Result<()>;
+    /// Check that the input did not end in the middle of a group
+    /// and flush the remaining bytes
+    fn finish(&mut self, offset: usize, output: &mut Vec<u8>) -> Result<()>;
+}
+
+/// Decoder for hex digits of either case
+#[derive(Debug, Default)]
+pub struct HexDecoder {
+    high: Option<u8>,
+}
+
+impl HexDecoder {
+    pub fn new() -> HexDecoder {
+        HexDecoder::default()
+    }
+}
+
+impl Decoder for HexDecoder {
+    fn push(&mut self, c: u8, offset: usize, output: &mut Vec<u8>) -> Result<()> {
+        let value = match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            b'A'..=b'F' => c - b'A' + 10,
+            _ => return Err(Error::InvalidCharacter { byte: c, offset }),
+        };
+        match self.high.take() {
+            Some(high) => output.push(high << 4 | value),
+            None => self.high = Some(value),
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, offset: usize, _output: &mut Vec<u8>) -> Result<()> {
+        if self.high.is_some() {
+            return Err(Error::InvalidEncoding(format!(
+                "Odd number of hex digits at offset {}",
+                offset
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Decoder for padded or unpadded base64
+#[derive(Debug)]
+pub struct Base64Decoder {
+    alphabet: Alphabet,
+    group: [u8; 4],
+    group_len: usize,
+    padding_len: usize,
+}
+
+impl Base64Decoder {
+    pub fn new(alphabet: Alphabet) -> Base64Decoder {
+        Base64Decoder {
+            alphabet,
+            group: [0; 4],
+            group_len: 0,
+            padding_len: 0,
+        }
+    }
+
+    fn flush_group(&mut self, output: &mut Vec<u8>) {
+        let g = self.group;
+        let bytes = [
+            g[0] << 2 | g[1] >> 4,
+            g[1] << 4 | g[2] >> 2,
+            g[2] << 6 | g[3],
+        ];
+        output.extend_from_slice(&bytes[..self.group_len - 1]);
+    }
+}
+
+impl Decoder for Base64Decoder {
+    fn push(&mut self, c: u8, offset: usize, output: &mut Vec<u8>) -> Result<()> {
+        if c == b'=' {
+            // padding may only complete a group of two or three characters
+            if self.group_len < 2 || self.group_len + self.padding_len >= 4 {
+                return Err(Error::InvalidCharacter { byte: c, offset });
+            }
+            self.padding_len += 1;
+            return Ok(());
+        }
+        let value = match self.alphabet.value(c) {
+            Some(value) if self.padding_len == 0 => value,
+            _ => return Err(Error::InvalidCharacter { byte: c, offset }),
+        };
+        self.group[self.group_len] = value;
+        self.group_len += 1;
+        if self.group_len == 4 {
+            self.flush_group(output);
+            self.group = [0; 4];
+            self.group_len = 0;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, offset: usize, output: &mut Vec<u8>) -> Result<()> {
+        match self.group_len {
+            0 => Ok(()),
+            1 => Err(Error::InvalidEncoding(format!(
+                "Truncated base64 group at offset {}",
+                offset
+            ))),
+            _ => {
+                if self.padding_len > 0 && self.group_len + self.padding_len != 4 {
+                    return Err(Error::InvalidEncoding(format!(
+                        "Incomplete base64 padding at offset {}",
+                        offset
+                    )));
+                }
+                self.flush_group(output);
+                self.group_len = 0;
+                self.padding_len = 0;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Run a decoder over a whole input
+fn decode_all<D: Decoder>(input: &[u8], mut decoder: D) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len());
+    for (offset, &c) in input.iter().enumerate() {
+        if !c.is_ascii_whitespace() {
+            decoder.push(c, offset, &mut output)?;
+        }
+    }
+    decoder.finish(input.len(), &mut output)?;
+    Ok(output)
+}
+
+/// Encode bytes as lowercase hex
+pub fn hex_encode<T: AsRef<[u8]>>(data: T) -> String {
+    hex_encode_with(data.as_ref(), HEX_LOWER)
+}
+
+/// Encode bytes as uppercase hex
+pub fn hex_encode_upper<T: AsRef<[u8]>>(data: T) -> String {
+    hex_encode_with(data.as_ref(), HEX_UPPER)
+}
+
+fn hex_encode_with(data: &[u8], digits: &[u8; 16]) -> String {
+    let mut result = String::with_capacity(data.len() * 2);
+    for b in data {
+        result.push(digits[(b >> 4) as usize] as char);
+        result.push(digits[(b & 0xf) as usize] as char);
+    }
+    result
+}
+
+/// Decode hex of either case
+pub fn hex_decode<T: AsRef<[u8]>>(input: T) -> Result<Vec<u8>> {
+    decode_all(input.as_ref(), HexDecoder::new())
+}
+
+/// Encode bytes as base64
+pub fn base64_encode<T: AsRef<[u8]>>(data: T, config: Base64Config) -> String {
+    let symbols = config.alphabet.symbols();
+    let data = data.as_ref();
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let indices = [
+            b[0] >> 2,
+            (b[0] & 0x03) << 4 | b[1] >> 4,
+            (b[1] & 0x0f) << 2 | b[2] >> 6,
+            b[2] & 0x3f,
+        ];
+        for &i in &indices[..chunk.len() + 1] {
+            result.push(symbols[i as usize] as char);
+        }
+        if config.padding {
+            for _ in chunk.len()..3 {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+/// Decode base64, with or without padding
+pub fn base64_decode<T: AsRef<[u8]>>(input: T, config: Base64Config) -> Result<Vec<u8>> {
+    decode_all(input.as_ref(), Base64Decoder::new(config.alphabet))
+}
+
+/// Convert a hex string into its base64 representation
+pub fn hex2base64(hex: &str) -> Result<String> {
+    let bytes = hex_decode(hex)?;
+    Ok(base64_encode(bytes, STANDARD))
+}
+
+/// Reader decoding the text read from the wrapped reader on the fly
+pub struct DecodeReader<R: Read, D: Decoder> {
+    inner: R,
+    decoder: D,
+    offset: usize,
+    output: Vec<u8>,
+    position: usize,
+    done: bool,
+    /// Decoding error returned again by every later read
+    failed: Option<Error>,
+}
+
+/// Copy of a decoding error, to report it on every read after it
+fn repeat_error(e: &Error) -> Error {
+    match e {
+        Error::InvalidCharacter { byte, offset } => Error::InvalidCharacter {
+            byte: *byte,
+            offset: *offset,
+        },
+        Error::InvalidEncoding(msg) => Error::InvalidEncoding(msg.clone()),
+        e => Error::InvalidEncoding(e.to_string()),
+    }
+}
+
+impl<R: Read, D: Decoder> DecodeReader<R, D> {
+    pub fn new(inner: R, decoder: D) -> DecodeReader<R, D> {
+        DecodeReader {
+            inner,
+            decoder,
+            offset: 0,
+            output: Vec::new(),
+            position: 0,
+            done: false,
+            failed: None,
+        }
+    }
+
+    fn fill_output(&mut self) -> Result<()> {
+        let mut chunk = [0; CHUNK_SIZE];
+        let n = loop {
+            match self.inner.read(&mut chunk) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        self.output.clear();
+        self.position = 0;
+        let result = if n == 0 {
+            self.done = true;
+            self.decoder.finish(self.offset, &mut self.output)
+        } else {
+            self.decode_chunk(&chunk[..n])
+        };
+        if let Err(e) = &result {
+            // Bytes decoded before the error are dropped along with the rest
+            self.output.clear();
+            self.failed = Some(repeat_error(e));
+        }
+        result
+    }
+
+    fn decode_chunk(&mut self, chunk: &[u8]) -> Result<()> {
+        for &c in chunk {
+            if !c.is_ascii_whitespace() {
+                self.decoder.push(c, self.offset, &mut self.output)?;
+            }
+            self.offset += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read, D: Decoder> Read for DecodeReader<R, D> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.output.len() {
+            if let Some(e) = &self.failed {
+                return Err(repeat_error(e).into());
+            }
+            if self.done {
+                return Ok(0);
+            }
+            self.fill_output()?;
+        }
+        let n = buf.len().min(self.output.len() - self.position);
+        buf[..n].copy_from_slice(&self.output[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// Read and decode a (possibly line wrapped) base64 file
+pub fn read_base64_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
+    let file = File::open(path)?;
+    let mut reader = DecodeReader::new(file, Base64Decoder::new(Alphabet::Standard));
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[test]
+fn test_hex2base64() {
+    let hex = "49276d206b696c6c696e6720796f757220627261696e206c696b65206120706f69736f6e6f7573206d757368726f6f6d";
+    assert_eq!(
+        hex2base64(hex).unwrap(),
+        "SSdtIGtpbGxpbmcgeW91ciBicmFpbiBsaWtlIGEgcG9pc29ub3VzIG11c2hyb29t"
+    );
+    assert!(hex2base64("zz").is_err());
+}
+
+#[test]
+fn test_hex() {
+    assert_eq!(hex_encode([0xde, 0xad, 0xbe, 0xef]), "deadbeef");
+    assert_eq!(hex_encode_upper([0xde, 0xad, 0xbe, 0xef]), "DEADBEEF");
+    assert_eq!(hex_decode("DEad be\nef").unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+    assert!(matches!(
+        hex_decode("dead bexf"),
+        Err(Error::InvalidCharacter {
+            byte: b'x',
+            offset: 7
+        })
+    ));
+    assert!(hex_decode("abc").is_err());
+}
+
+#[test]
+fn test_base64_rfc_4648_vectors() {
+    let vectors = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+    for (plain, encoded) in vectors.iter() {
+        assert_eq!(base64_encode(plain, STANDARD), *encoded);
+        assert_eq!(base64_decode(encoded, STANDARD).unwrap(), plain.as_bytes());
+        let unpadded = encoded.trim_end_matches('=');
+        assert_eq!(base64_encode(plain, STANDARD_NO_PAD), unpadded);
+        assert_eq!(base64_decode(unpadded, STANDARD).unwrap(), plain.as_bytes());
+    }
+}
+
+#[test]
+fn test_base64_alphabets_and_errors() {
+    let data = [0xfb, 0xff, 0xbf];
+    assert_eq!(base64_encode(data, STANDARD), "+/+/");
+    assert_eq!(base64_encode(data, URL_SAFE), "-_-_");
+    assert_eq!(base64_decode("-_-_", URL_SAFE_NO_PAD).unwrap(), data);
+    assert!(matches!(
+        base64_decode("Zm9v\nYm-y", STANDARD),
+        Err(Error::InvalidCharacter {
+            byte: b'-',
+            offset: 7
+        })
+    ));
+    assert!(base64_decode("Zm9vY", STANDARD).is_err());
+    assert!(base64_decode("Zg=a", STANDARD).is_err());
+    assert!(base64_decode("Zg=", STANDARD).is_err());
+}
+
+#[test]
+fn test_decode_reader() {
+    let encoded = base64_encode(vec![42; 10000], STANDARD);
+    let wrapped: Vec<u8> = encoded
+        .as_bytes()
+        .chunks(60)
+        .flat_map(|line| line.iter().copied().chain(Some(b'\n')))
+        .collect();
+    let mut reader = DecodeReader::new(&wrapped[..], Base64Decoder::new(Alphabet::Standard));
+    let mut decoded = Vec::new();
+    reader.read_to_end(&mut decoded).unwrap();
+    assert_eq!(decoded, vec![42; 10000]);
+
+    let mut reader = DecodeReader::new(&b"00ff0g11"[..], HexDecoder::new());
+    let mut decoded = Vec::new();
+    let e = reader.read_to_end(&mut decoded).unwrap_err();
+    assert!(matches!(
+        Error::from(e),
+        Error::InvalidCharacter {
+            byte: b'g',
+            offset: 5
+        }
+    ));
+    // Neither the bytes before the error nor the input after it are
+    // served by later reads, which keep returning the error
+    let e = reader.read_to_end(&mut decoded).unwrap_err();
+    assert!(matches!(
+        Error::from(e),
+        Error::InvalidCharacter { offset: 5, .. }
+    ));
+    assert!(decoded.is_empty());
+
+    let mut reader = DecodeReader::new(&b"Zm9vY"[..], Base64Decoder::new(Alphabet::Standard));
+    assert!(reader.read_to_end(&mut decoded).is_err());
+    assert!(reader.read_to_end(&mut decoded).is_err());
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+use std::io;
+use std::string::FromUtf8Error;
+
+/// Errors returned by the cryptopals primitives and attacks
+#[derive(Debug)]
+pub enum Error {
+    /// Input is not valid hex, base64 or UTF-8
+    InvalidEncoding(String),
+    /// Hex or base64 input contains an invalid character
+    InvalidCharacter { byte: u8, offset: usize },
+    /// A message, key, IV or block has the wrong length
+    BadLength(String),
+    /// The padding of a decrypted message is malformed
+    BadPadding,
+    /// Reading or writing data failed
+    Io(io::Error),
+    /// The block cipher rejected its parameters
+    Crypto(String),
+    /// An attack could not recover what it was after
+    AttackFailed(String),
+}
+
+/// Result type used throughout the crate
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidEncoding(msg) => write!(f, "Invalid encoding: {}", msg),
+            Error::InvalidCharacter { byte, offset } => write!(
+                f,
+                "Invalid character {:?} at offset {}",
+                *byte as char, offset
+            ),
+            Error::BadLength(msg) => write!(f, "Bad length: {}", msg),
+            Error::BadPadding => write!(f, "Bad padding"),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Crypto(msg) => write!(f, "Crypto error: {}", msg),
+            Error::AttackFailed(msg) => write!(f, "Attack failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Crate errors wrapped into an `io::Error` by a reader or writer are unwrapped
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        if e.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            *e.into_inner().unwrap().downcast::<Error>().unwrap()
+        } else {
+            Error::Io(e)
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> io::Error {
+        match e {
+            Error::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+impl From<FromUtf8Error> for Error {
+    fn from(e: FromUtf8Error) -> Error {
+        Error::InvalidEncoding(e.to_string())
+    }
+}
@@ -0,0 +1,344 @@
+//! Recovering repeating XOR keys from the known bytes of file formats.
+//!
+//! Most file formats start with a magic number, and many have other
+//! bytes that hardly ever change, such as the end of a PNG file or the
+//! padding of an ELF header. XORing them with the ciphertext gives the
+//! key bytes at those offsets. Known bytes that fall on the same key
+//! byte for a given key size must agree, which rules out key sizes, and
+//! the key bytes left unknown are solved column by column as in
+//! `repeating_xor`.
+use crate::error::{Error, Result};
+use crate::repeating_xor::{
+    best_trial, estimate_key_sizes, reduce_key, Blocks, KeySizeOptions, Solution,
+};
+use crate::scoring::{LogLikelihood, Scorer};
+use crate::xor::repeating_key_xor;
+
+/// Where known bytes sit in a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    /// Offset from the start of the file
+    Start(usize),
+    /// Number of bytes before the end of the file
+    End(usize),
+}
+
+/// Bytes found at fixed positions in every file of a format
+#[derive(Debug)]
+pub struct Signature {
+    pub name: &'static str,
+    pub fragments: &'static [(Position, &'static [u8])],
+    /// Whether the rest of the file is binary data rather than text
+    pub binary: bool,
+}
+
+impl Signature {
+    /// Look a signature up by name, ignoring case
+    pub fn find(name: &str) -> Option<&'static Signature> {
+        SIGNATURES
+            .iter()
+            .find(|signature| signature.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The scorer suited to the contents of the format
+    pub fn scorer(&self) -> Box<dyn Scorer> {
+        if self.binary {
+            Box::new(ZeroBytes)
+        } else {
+            Box::new(LogLikelihood::default())
+        }
+    }
+
+    /// Key bytes at the positions of the signature, assuming the
+    /// ciphertext is a file of this format. Fragments falling outside
+    /// the ciphertext are left out.
+    pub fn key_bytes(&self, ciphertext: &[u8]) -> Vec<(usize, u8)> {
+        let mut known = Vec::new();
+        for &(position, bytes) in self.fragments {
+            let start = match position {
+                Position::Start(offset) => Some(offset),
+                Position::End(offset) => ciphertext.len().checked_sub(offset),
+            };
+            match start {
+                Some(start) if start + bytes.len() <= ciphertext.len() => {
+                    let ciphertext = &ciphertext[start..];
+                    known.extend(
+                        bytes
+                            .iter()
+                            .zip(ciphertext)
+                            .enumerate()
+                            .map(|(i, (p, c))| (start + i, p ^ c)),
+                    );
+                }
+                _ => {}
+            }
+        }
+        known
+    }
+}
+
+/// Known file formats, with their magic numbers and other fixed bytes
+pub const SIGNATURES: &[Signature] = &[
+    Signature {
+        name: "png",
+        fragments: &[
+            // Magic number, then the length and type of the IHDR chunk
+            (Position::Start(0), b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR"),
+            // Empty IEND chunk along with its CRC
+            (Position::End(12), b"\x00\x00\x00\x00IEND\xaeB`\x82"),
+        ],
+        binary: true,
+    },
+    Signature {
+        name: "zip",
+        fragments: &[
+            (Position::Start(0), b"PK\x03\x04"),
+            // End of central directory record of a single disk archive
+            // without a comment
+            (Position::End(22), b"PK\x05\x06\x00\x00\x00\x00"),
+        ],
+        binary: true,
+    },
+    Signature {
+        name: "pdf",
+        fragments: &[(Position::Start(0), b"%PDF-1.")],
+        binary: false,
+    },
+    Signature {
+        name: "elf64",
+        fragments: &[
+            // Identification of a little-endian 64-bit file, with the
+            // System V ABI and zero padding
+            (
+                Position::Start(0),
+                b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00",
+            ),
+            // Version, then the size of the ELF header
+            (Position::Start(20), b"\x01\x00\x00\x00"),
+            (Position::Start(52), b"\x40\x00\x38\x00"),
+        ],
+        binary: true,
+    },
+    Signature {
+        name: "elf32",
+        fragments: &[
+            (
+                Position::Start(0),
+                b"\x7fELF\x01\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00",
+            ),
+            (Position::Start(20), b"\x01\x00\x00\x00"),
+            (Position::Start(40), b"\x34\x00\x20\x00"),
+        ],
+        binary: true,
+    },
+    Signature {
+        name: "gzip",
+        fragments: &[(Position::Start(0), b"\x1f\x8b\x08")],
+        binary: true,
+    },
+    Signature {
+        name: "gif",
+        fragments: &[(Position::Start(0), b"GIF89a")],
+        binary: true,
+    },
+    Signature {
+        name: "jpeg",
+        fragments: &[(Position::Start(0), b"\xff\xd8\xff")],
+        binary: true,
+    },
+];
+
+/// Scores binary data by its share of bytes other than zero, since zero
+/// is by far the most common byte of executables and many binary formats.
+/// Lower is better.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZeroBytes;
+
+impl Scorer for ZeroBytes {
+    fn score(&self, text: &[u8]) -> f32 {
+        if text.is_empty() {
+            return f32::MAX;
+        }
+        let zeros = text.iter().filter(|&&b| b == 0).count();
+        1.0 - zeros as f32 / text.len() as f32
+    }
+}
+
+/// Fold known key bytes onto a key of `key_size` bytes. Returns `None`
+/// when two known bytes falling on the same key byte disagree, along
+/// with the number of known bytes checked against another one.
+pub fn fold_key(known: &[(usize, u8)], key_size: usize) -> Option<(Vec<Option<u8>>, usize)> {
+    let mut key = vec![None; key_size];
+    let mut checked = 0;
+    for &(position, byte) in known {
+        match key[position % key_size] {
+            Some(k) if k != byte => return None,
+            Some(_) => checked += 1,
+            None => key[position % key_size] = Some(byte),
+        }
+    }
+    Some((key, checked))
+}
+
+/// Formats whose known bytes agree with each other for some key size in
+/// the range of the options, along with the largest number of known
+/// bytes checked for one key size, best first. Formats whose known
+/// bytes never fall on the same key byte cannot be checked and are left out.
+pub fn detect_formats(
+    ciphertext: &[u8],
+    options: &KeySizeOptions,
+) -> Vec<(&'static Signature, usize)> {
+    let max_size = options.max_size.min(ciphertext.len() / 2);
+    let mut formats: Vec<(&'static Signature, usize)> = SIGNATURES
+        .iter()
+        .filter_map(|signature| {
+            let known = signature.key_bytes(ciphertext);
+            (options.min_size.max(1)..=max_size)
+                .filter_map(|key_size| fold_key(&known, key_size).map(|(_, checked)| checked))
+                .max()
+                .filter(|&checked| checked > 0)
+                .map(|checked| (signature, checked))
+        })
+        .collect();
+    formats.sort_by_key(|&(_, checked)| std::cmp::Reverse(checked));
+    formats
+}
+
+/// Recover the key of a file of the given format encrypted with
+/// repeating-key XOR. Key sizes are ranked as in `repeating_xor`, those
+/// the known bytes rule out are skipped, and the best `options.trials`
+/// of the others are solved, filling in the key bytes that are not
+/// known with `scorer`. Known bytes checked against each other are
+/// stronger evidence than scores, so only the key sizes checking the
+/// most of them are tried, which usually leaves very few.
+pub fn recover_key_with<S: Scorer + ?Sized>(
+    ciphertext: &[u8],
+    signature: &Signature,
+    scorer: &S,
+    options: &KeySizeOptions,
+) -> Result<Solution> {
+    let known = signature.key_bytes(ciphertext);
+    let folded: Vec<(usize, Vec<Option<u8>>, usize)> = estimate_key_sizes(ciphertext, options)?
+        .into_iter()
+        .filter_map(|(key_size, _)| {
+            fold_key(&known, key_size).map(|(key, checked)| (key_size, key, checked))
+        })
+        .collect();
+    let most_checked = folded.iter().map(|&(_, _, checked)| checked).max();
+    let mut trials = Vec::new();
+    for (key_size, key, _) in folded
+        .into_iter()
+        .filter(|&(_, _, checked)| Some(checked) == most_checked)
+        .take(options.trials.max(1))
+    {
+        let mut solution = Blocks::new(ciphertext.to_vec(), key_size)?.solve_with(scorer);
+        for (i, byte) in key.iter().enumerate() {
+            if let Some(byte) = *byte {
+                solution.key[i] = byte;
+                solution.confidence[i] = 1.0;
+            }
+        }
+        solution.plaintext = repeating_key_xor(ciphertext, &solution.key)?;
+        reduce_key(&mut solution);
+        trials.push((scorer.score(&solution.plaintext), solution));
+    }
+    best_trial(trials, Solution::key_size).ok_or_else(|| {
+        Error::AttackFailed(format!(
+            "The known bytes of {} rule out every key size",
+            signature.name
+        ))
+    })
+}
+
+/// Recover the key of a file of the given format, scoring
+/// the unknown bytes with the scorer suited to the format
+pub fn recover_key(
+    ciphertext: &[u8],
+    signature: &Signature,
+    options: &KeySizeOptions,
+) -> Result<Solution> {
+    recover_key_with(ciphertext, signature, signature.scorer().as_ref(), options)
+}
+
+/// Recover the key of a file of unknown format, trying the format
+/// `detect_formats` ranks first
+pub fn recover_key_auto(
+    ciphertext: &[u8],
+    options: &KeySizeOptions,
+) -> Result<(&'static Signature, Solution)> {
+    let (signature, _) = detect_formats(ciphertext, options)
+        .into_iter()
+        .next()
+        .ok_or_else(|| {
+            Error::AttackFailed(
+                "No known format matches, the key may be longer than the known bytes".to_string(),
+            )
+        })?;
+    Ok((signature, recover_key(ciphertext, signature, options)?))
+}
+
+/// A small PNG file: header, an IDAT chunk of made up data and the end
+#[cfg(test)]
+fn sample_png() -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+    png.extend_from_slice(b"\x00\x00\x01\x00\x00\x00\x01\x00\x08\x06\x00\x00\x00\x5c\x72\xa8\x66");
+    png.extend_from_slice(b"\x00\x00\x01\x00IDAT");
+    png.extend(crate::random::random_bytes(
+        &mut crate::random::seeded_rng(21),
+        256,
+    ));
+    png.extend_from_slice(b"\x00\x00\x00\x00IEND\xaeB`\x82");
+    png
+}
+
+#[test]
+fn test_recover_png_key() {
+    let png = sample_png();
+    let ciphertext = repeating_key_xor(&png, b"s3cr3t!").unwrap();
+    let options = KeySizeOptions::default();
+    let solution = recover_key(&ciphertext, Signature::find("PNG").unwrap(), &options).unwrap();
+    assert_eq!(solution.key, b"s3cr3t!");
+    assert_eq!(solution.plaintext, png);
+
+    let formats = detect_formats(&ciphertext, &options);
+    assert_eq!(formats[0].0.name, "png");
+    let (signature, solution) = recover_key_auto(&ciphertext, &options).unwrap();
+    assert_eq!(signature.name, "png");
+    assert_eq!(solution.key, b"s3cr3t!");
+}
+
+#[test]
+fn test_recover_elf_key() {
+    // An ELF header followed by mostly zero bytes, with a key longer
+    // than the 16 known bytes at the start, so that the key bytes 16 to
+    // 19 are only found by solving their columns
+    let mut elf = b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    elf.extend_from_slice(b"\x02\x00\x3e\x00\x01\x00\x00\x00");
+    elf.resize(52, 0);
+    elf.extend_from_slice(b"\x40\x00\x38\x00");
+    elf.resize(2000, 0);
+    for i in (100..2000).step_by(7) {
+        elf[i] = (i % 251) as u8;
+    }
+    let key = b"a much longer elf key";
+    let ciphertext = repeating_key_xor(&elf, key).unwrap();
+    let options = KeySizeOptions::default();
+    let signature = Signature::find("elf64").unwrap();
+    let (folded, _) = fold_key(&signature.key_bytes(&ciphertext), key.len()).unwrap();
+    assert!(folded[16..20].iter().all(Option::is_none));
+    let solution = recover_key(&ciphertext, signature, &options).unwrap();
+    assert_eq!(solution.key, key);
+    assert_eq!(solution.plaintext, elf);
+}
+
+#[test]
+fn test_fold_key() {
+    let known = [(0, 1), (1, 2), (4, 1), (5, 2)];
+    assert!(fold_key(&known, 3).is_none());
+    assert_eq!(
+        fold_key(&known, 4).unwrap(),
+        (vec![Some(1), Some(2), None, None], 2)
+    );
+    assert_eq!(fold_key(&known, 6).unwrap().1, 0);
+    assert!(detect_formats(b"not a known format at all", &KeySizeOptions::default()).is_empty());
+}
@@ -0,0 +1,26 @@
+//! Primitives shared by the cryptopals challenge binaries.
+pub mod aes;
+pub mod bits;
+pub mod cipher;
+pub mod classical;
+pub mod cookie;
+pub mod crib;
+pub mod ctr;
+pub mod detection;
+pub mod encoding;
+pub mod error;
+pub mod known_plaintext;
+pub mod many_time_pad;
+pub mod modes;
+pub mod ngram;
+pub mod oracle;
+pub mod padding;
+pub mod profiles;
+pub mod random;
+pub mod repeating_xor;
+pub mod scan;
+pub mod scoring;
+pub mod stream;
+pub mod xor;
+
+pub use error::{Error, Result};
@@ -0,0 +1,166 @@
+//! Breaking ciphertexts encrypted with the same keystream, as produced
+//! by a reused one-time pad or CTR mode with a fixed nonce.
+//!
+//! Byte `i` of every ciphertext is XORed with keystream byte `i`, so the
+//! ciphertexts are lined up in columns and each column is solved as
+//! single-byte XOR, like the blocks of a repeating-key XOR ciphertext.
+//! Flipping bit 5 of a keystream byte flips the case of every letter of
+//! its column, so columns made mostly of capitals, such as the first one
+//! of a set of sentences, can come out in the wrong case. Such columns
+//! get a low confidence.
+use crate::error::{Error, Result};
+use crate::repeating_xor::{solve_key_bytes, KeyedText, CONTEXT};
+use crate::scoring::{LogLikelihood, Scorer};
+
+/// How ciphertexts of different lengths are lined up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    /// Cut every ciphertext to the length of the shortest one,
+    /// so every keystream byte is recovered from all of them
+    Truncate,
+    /// Keep every byte, the last keystream bytes being recovered
+    /// from the few ciphertexts that are long enough
+    Columns,
+}
+
+/// Keystream and plaintexts recovered from ciphertexts sharing a keystream
+#[derive(Debug, Clone)]
+pub struct Solution {
+    pub keystream: Vec<u8>,
+    /// Confidence in each keystream byte, see `repeating_xor::Solution`
+    pub confidence: Vec<f32>,
+    /// The plaintexts, in the order of the ciphertexts
+    pub plaintexts: Vec<Vec<u8>>,
+}
+
+/// Recover the keystream shared by the ciphertexts, scored against English
+pub fn solve<T: AsRef<[u8]>>(ciphertexts: &[T], alignment: Alignment) -> Result<Solution> {
+    solve_with(ciphertexts, alignment, &LogLikelihood::default())
+}
+
+/// Same as `solve`, rating the candidates of each column with `scorer`.
+/// As in `Blocks::solve_with`, columns whose best key bytes are too close
+/// to call are solved again with the neighbouring plaintext bytes in view.
+pub fn solve_with<T: AsRef<[u8]>, S: Scorer + ?Sized>(
+    ciphertexts: &[T],
+    alignment: Alignment,
+    scorer: &S,
+) -> Result<Solution> {
+    if ciphertexts.len() < 2 {
+        return Err(Error::AttackFailed(
+            "A many-time pad needs at least two ciphertexts".to_string(),
+        ));
+    }
+    let lengths = ciphertexts.iter().map(|c| c.as_ref().len());
+    let len = match alignment {
+        Alignment::Truncate => lengths.min(),
+        Alignment::Columns => lengths.max(),
+    }
+    .unwrap_or(0);
+    let rows: Vec<&[u8]> = ciphertexts
+        .iter()
+        .map(|c| &c.as_ref()[..c.as_ref().len().min(len)])
+        .collect();
+
+    let columns: Vec<Vec<u8>> = (0..len).map(|i| column(&rows, i)).collect();
+    let mut plaintexts: Vec<Vec<u8>> = rows.iter().map(|row| row.to_vec()).collect();
+    let mut text = Columns {
+        rows: &rows,
+        plaintexts: &mut plaintexts,
+    };
+    let (keystream, confidence) = solve_key_bytes(&mut text, &columns, scorer);
+    Ok(Solution {
+        keystream,
+        confidence,
+        plaintexts,
+    })
+}
+
+/// Byte `i` of every ciphertext long enough to have one
+fn column(rows: &[&[u8]], i: usize) -> Vec<u8> {
+    rows.iter().filter_map(|row| row.get(i).copied()).collect()
+}
+
+/// Plaintexts of ciphertexts sharing a keystream, keystream
+/// byte `i` covering column `i`
+struct Columns<'a> {
+    rows: &'a [&'a [u8]],
+    plaintexts: &'a mut [Vec<u8>],
+}
+
+impl KeyedText for Columns<'_> {
+    fn decrypt(&mut self, i: usize, key: u8) {
+        for (plaintext, row) in self.plaintexts.iter_mut().zip(self.rows) {
+            if let Some(&c) = row.get(i) {
+                plaintext[i] = c ^ key;
+            }
+        }
+    }
+
+    fn windows(&self, i: usize, windows: &mut Vec<u8>) {
+        for plaintext in self.plaintexts.iter().filter(|p| p.len() > i) {
+            let start = i.saturating_sub(CONTEXT);
+            let end = (i + CONTEXT + 1).min(plaintext.len());
+            windows.extend_from_slice(&plaintext[start..end]);
+        }
+    }
+}
+
+/// Lines of the chal6 plaintext encrypted with the same random keystream
+#[cfg(test)]
+fn reused_pad_ciphertexts() -> (Vec<Vec<u8>>, Vec<Vec<u8>>) {
+    use crate::random::{random_bytes, seeded_rng};
+    use crate::xor::repeating_key_xor;
+    let ciphertext = crate::encoding::read_base64_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/set1/chal6/data/ciphertext.txt"
+    ))
+    .unwrap();
+    let text = repeating_key_xor(&ciphertext, b"Terminator X: Bring the noise").unwrap();
+    let plaintexts: Vec<Vec<u8>> = text
+        .split(|&b| b == b'\n')
+        .map(|line| line.trim_ascii().to_vec())
+        .filter(|line| line.len() >= 20)
+        .collect();
+    let keystream = random_bytes(&mut seeded_rng(19), 100);
+    let ciphertexts = plaintexts
+        .iter()
+        .map(|p| p.iter().zip(&keystream).map(|(a, b)| a ^ b).collect())
+        .collect();
+    (plaintexts, ciphertexts)
+}
+
+#[test]
+fn test_solve_truncated() {
+    let (plaintexts, ciphertexts) = reused_pad_ciphertexts();
+    let shortest = plaintexts.iter().map(|p| p.len()).min().unwrap();
+    let solution = solve(&ciphertexts, Alignment::Truncate).unwrap();
+    assert_eq!(solution.keystream.len(), shortest);
+    // Capitals, digits and quotes start the lines, so the first column
+    // is too close to call: it is flagged as such and comes out with
+    // bit 5 flipped, while every other column is recovered
+    assert!(solution.confidence[0] < 0.1);
+    for (recovered, plaintext) in solution.plaintexts.iter().zip(&plaintexts) {
+        let mut expected = plaintext[..shortest].to_vec();
+        expected[0] ^= 0x20;
+        assert_eq!(recovered[..], expected[..]);
+    }
+}
+
+#[test]
+fn test_solve_columns() {
+    let (plaintexts, ciphertexts) = reused_pad_ciphertexts();
+    let solution = solve(&ciphertexts, Alignment::Columns).unwrap();
+    let longest = plaintexts.iter().map(|p| p.len()).max().unwrap();
+    assert_eq!(solution.keystream.len(), longest);
+    assert_eq!(solution.confidence.len(), longest);
+    let total: usize = plaintexts.iter().map(|p| p.len()).sum();
+    let correct: usize = solution
+        .plaintexts
+        .iter()
+        .zip(&plaintexts)
+        .map(|(r, p)| r.iter().zip(p).filter(|(a, b)| a == b).count())
+        .sum();
+    assert!(correct * 100 >= total * 95, "{} of {}", correct, total);
+    assert!(solve(&ciphertexts[..1], Alignment::Columns).is_err());
+}
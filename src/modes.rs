@@ -0,0 +1,474 @@
+use crate::aes::Aes;
+pub use crate::aes::BLOCK_SIZE;
+use crate::cipher::BlockCipher;
+use crate::error::{Error, Result};
+use crate::padding::Padding;
+use crate::xor::xor;
+use rand::Rng;
+
+/// Check that the input is made of whole blocks
+fn check_blocks(input: &[u8], blocksize: usize) -> Result<()> {
+    if !input.len().is_multiple_of(blocksize) {
+        return Err(Error::BadLength(format!(
+            "Input length {} is not a multiple of the blocksize {}",
+            input.len(),
+            blocksize
+        )));
+    }
+    Ok(())
+}
+
+/// Check that the IV is exactly one block long
+fn check_iv(iv: &[u8], blocksize: usize) -> Result<()> {
+    if iv.len() != blocksize {
+        return Err(Error::BadLength(format!(
+            "IV length {} does not match the blocksize {}",
+            iv.len(),
+            blocksize
+        )));
+    }
+    Ok(())
+}
+
+/// Encrypt a byte slice in ECB mode with any block cipher.
+/// The input is expected to be padded already.
+pub fn encrypt_ecb<C: BlockCipher>(cipher: &C, plaintext: &[u8]) -> Result<Vec<u8>> {
+    check_blocks(plaintext, cipher.block_size())?;
+    let mut ciphertext: Vec<u8> = Vec::with_capacity(plaintext.len());
+    for block in split_blocks(plaintext, cipher.block_size()) {
+        let mut block = block.to_vec();
+        cipher.encrypt_block(&mut block);
+        ciphertext.extend(block);
+    }
+    Ok(ciphertext)
+}
+
+/// Decrypt a byte slice in ECB mode with any block cipher.
+/// Padding is left in place.
+pub fn decrypt_ecb<C: BlockCipher>(cipher: &C, ciphertext: &[u8]) -> Result<Vec<u8>> {
+    check_blocks(ciphertext, cipher.block_size())?;
+    let mut plaintext: Vec<u8> = Vec::with_capacity(ciphertext.len());
+    for block in split_blocks(ciphertext, cipher.block_size()) {
+        let mut block = block.to_vec();
+        cipher.decrypt_block(&mut block);
+        plaintext.extend(block);
+    }
+    Ok(plaintext)
+}
+
+/// Encrypt a byte slice in CBC mode with any block cipher.
+/// The input is expected to be padded already.
+pub fn encrypt_cbc<C: BlockCipher>(cipher: &C, plaintext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+    check_blocks(plaintext, cipher.block_size())?;
+    check_iv(iv, cipher.block_size())?;
+    let mut ciphertext: Vec<u8> = Vec::with_capacity(plaintext.len());
+    let mut prev_block = iv.to_vec();
+    for block in split_blocks(plaintext, cipher.block_size()) {
+        let mut current_block = xor(block, &prev_block)?;
+        cipher.encrypt_block(&mut current_block);
+        ciphertext.extend(&current_block);
+        prev_block = current_block;
+    }
+    Ok(ciphertext)
+}
+
+/// Decrypt a byte slice in CBC mode with any block cipher.
+/// Padding is left in place.
+pub fn decrypt_cbc<C: BlockCipher>(cipher: &C, ciphertext: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+    check_blocks(ciphertext, cipher.block_size())?;
+    check_iv(iv, cipher.block_size())?;
+    let mut plaintext: Vec<u8> = Vec::with_capacity(ciphertext.len());
+    let mut prev_block = iv;
+    for block in split_blocks(ciphertext, cipher.block_size()) {
+        let mut current_block = block.to_vec();
+        cipher.decrypt_block(&mut current_block);
+        plaintext.extend(xor(&current_block, prev_block)?);
+        prev_block = block;
+    }
+    Ok(plaintext)
+}
+
+/// Size of the segments of plaintext encrypted at a time in CFB mode.
+/// Each ciphertext segment is shifted into the input of the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfbSegment {
+    /// CFB-1, one block cipher call per bit
+    Bit,
+    /// CFB-8, one block cipher call per byte
+    Byte,
+    /// CFB-128 for AES, a whole block at a time
+    Block,
+}
+
+/// Shift a register one bit to the left, shifting `bit` in on the right
+fn shift_in_bit(register: &mut [u8], bit: u8) {
+    for i in 0..register.len() {
+        let next = register.get(i + 1).map_or(bit, |b| b >> 7);
+        register[i] = (register[i] << 1) | next;
+    }
+}
+
+/// CFB encryption or decryption, which only differ in whether the
+/// input or the output is fed back into the register
+fn cfb<C: BlockCipher>(
+    cipher: &C,
+    input: &[u8],
+    iv: &[u8],
+    segment: CfbSegment,
+    decrypt: bool,
+) -> Result<Vec<u8>> {
+    check_iv(iv, cipher.block_size())?;
+    let mut register = iv.to_vec();
+    let mut output = Vec::with_capacity(input.len());
+    match segment {
+        CfbSegment::Bit => {
+            for &byte in input {
+                let mut out = 0;
+                for i in (0..8).rev() {
+                    let mut keystream = register.clone();
+                    cipher.encrypt_block(&mut keystream);
+                    let in_bit = (byte >> i) & 1;
+                    let out_bit = in_bit ^ (keystream[0] >> 7);
+                    out |= out_bit << i;
+                    shift_in_bit(&mut register, if decrypt { in_bit } else { out_bit });
+                }
+                output.push(out);
+            }
+        }
+        CfbSegment::Byte => {
+            for &byte in input {
+                let mut keystream = register.clone();
+                cipher.encrypt_block(&mut keystream);
+                let out = byte ^ keystream[0];
+                register.rotate_left(1);
+                *register.last_mut().expect("IV is not empty") = if decrypt { byte } else { out };
+                output.push(out);
+            }
+        }
+        CfbSegment::Block => {
+            for block in input.chunks(cipher.block_size()) {
+                cipher.encrypt_block(&mut register);
+                let out: Vec<u8> = block.iter().zip(&register).map(|(b, k)| b ^ k).collect();
+                register[..block.len()].copy_from_slice(if decrypt { block } else { &out });
+                output.extend(out);
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Encrypt a byte slice in CFB mode with any block cipher.
+/// No padding is needed, a final partial segment is
+/// XORed with the start of its keystream block.
+pub fn encrypt_cfb<C: BlockCipher>(
+    cipher: &C,
+    plaintext: &[u8],
+    iv: &[u8],
+    segment: CfbSegment,
+) -> Result<Vec<u8>> {
+    cfb(cipher, plaintext, iv, segment, false)
+}
+
+/// Decrypt a byte slice in CFB mode with any block cipher
+pub fn decrypt_cfb<C: BlockCipher>(
+    cipher: &C,
+    ciphertext: &[u8],
+    iv: &[u8],
+    segment: CfbSegment,
+) -> Result<Vec<u8>> {
+    cfb(cipher, ciphertext, iv, segment, true)
+}
+
+/// Encrypt or decrypt a byte slice in OFB mode with any block cipher.
+/// The keystream is the IV encrypted over and over, so encryption and
+/// decryption are the same operation and no padding is needed.
+pub fn ofb<C: BlockCipher>(cipher: &C, input: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+    check_iv(iv, cipher.block_size())?;
+    let mut keystream = iv.to_vec();
+    let mut output = Vec::with_capacity(input.len());
+    for block in input.chunks(cipher.block_size()) {
+        cipher.encrypt_block(&mut keystream);
+        output.extend(block.iter().zip(&keystream).map(|(b, k)| b ^ k));
+    }
+    Ok(output)
+}
+
+/// Key size of AES-128 in bytes
+pub const AES_128_KEY_SIZE: usize = 16;
+
+/// AES cipher for the `*_aes_128_*` helpers, which only take 128 bit keys
+pub fn aes_128(key: &[u8]) -> Result<Aes> {
+    if key.len() != AES_128_KEY_SIZE {
+        return Err(Error::BadLength(format!(
+            "AES-128 key length must be {}, got {}",
+            AES_128_KEY_SIZE,
+            key.len()
+        )));
+    }
+    Aes::new(key)
+}
+
+/// Pad and encrypt a byte slice with AES-128 in ECB mode,
+/// drawing the random bytes of ISO 10126 padding from `rng`
+pub fn encrypt_aes_128_ecb<R: Rng + ?Sized>(
+    plaintext: &[u8],
+    key: &[u8],
+    padding: Padding,
+    rng: &mut R,
+) -> Result<Vec<u8>> {
+    encrypt_ecb(
+        &aes_128(key)?,
+        &padding.pad_with_rng(plaintext, BLOCK_SIZE, rng)?,
+    )
+}
+
+/// Decrypt a byte slice with AES-128 in ECB mode and remove its padding
+pub fn decrypt_aes_128_ecb(ciphertext: &[u8], key: &[u8], padding: Padding) -> Result<Vec<u8>> {
+    padding.unpad(&decrypt_ecb(&aes_128(key)?, ciphertext)?, BLOCK_SIZE)
+}
+
+/// Split a byte slice into blocks of size blocksize,
+/// dropping any trailing partial block
+pub fn split_blocks(ciphertext: &[u8], blocksize: usize) -> Vec<&[u8]> {
+    ciphertext.chunks_exact(blocksize).collect()
+}
+
+/// Decrypt a byte slice with AES-128 in CBC mode and remove its padding
+pub fn decrypt_aes_128_cbc(
+    ciphertext: &[u8],
+    iv: &[u8],
+    key: &[u8],
+    padding: Padding,
+) -> Result<Vec<u8>> {
+    padding.unpad(&decrypt_cbc(&aes_128(key)?, ciphertext, iv)?, BLOCK_SIZE)
+}
+
+/// Pad and encrypt a byte slice with AES-128 in CBC mode,
+/// drawing the random bytes of ISO 10126 padding from `rng`
+pub fn encrypt_aes_128_cbc<R: Rng + ?Sized>(
+    plaintext: &[u8],
+    iv: &[u8],
+    key: &[u8],
+    padding: Padding,
+    rng: &mut R,
+) -> Result<Vec<u8>> {
+    encrypt_cbc(
+        &aes_128(key)?,
+        &padding.pad_with_rng(plaintext, BLOCK_SIZE, rng)?,
+        iv,
+    )
+}
+
+/// Encrypt a byte slice with AES-128 in CFB mode
+pub fn encrypt_aes_128_cfb(
+    plaintext: &[u8],
+    iv: &[u8],
+    key: &[u8],
+    segment: CfbSegment,
+) -> Result<Vec<u8>> {
+    encrypt_cfb(&aes_128(key)?, plaintext, iv, segment)
+}
+
+/// Decrypt a byte slice with AES-128 in CFB mode
+pub fn decrypt_aes_128_cfb(
+    ciphertext: &[u8],
+    iv: &[u8],
+    key: &[u8],
+    segment: CfbSegment,
+) -> Result<Vec<u8>> {
+    decrypt_cfb(&aes_128(key)?, ciphertext, iv, segment)
+}
+
+/// Encrypt or decrypt a byte slice with AES-128 in OFB mode
+pub fn aes_128_ofb(input: &[u8], iv: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    ofb(&aes_128(key)?, input, iv)
+}
+
+/// Toy 8 byte block cipher used to exercise the generic modes
+#[cfg(test)]
+struct XorRotate(u8);
+
+#[cfg(test)]
+impl BlockCipher for XorRotate {
+    fn block_size(&self) -> usize {
+        8
+    }
+    fn encrypt_block(&self, block: &mut [u8]) {
+        block.iter_mut().for_each(|b| *b ^= self.0);
+        block.rotate_left(1);
+    }
+    fn decrypt_block(&self, block: &mut [u8]) {
+        block.rotate_right(1);
+        block.iter_mut().for_each(|b| *b ^= self.0);
+    }
+}
+
+#[test]
+fn test_ecb_encrypt_decrypt() {
+    let mut rng = crate::random::seeded_rng(0);
+    let msg = b"YELLOW SUBMARINEYELLOW SUBMARINE";
+    let key = b"YELLOW SUBMARINE";
+    let ciphertext = encrypt_aes_128_ecb(msg, key, Padding::None, &mut rng).unwrap();
+    assert_eq!(ciphertext[..BLOCK_SIZE], ciphertext[BLOCK_SIZE..]);
+    assert_eq!(
+        decrypt_aes_128_ecb(&ciphertext, key, Padding::None).unwrap(),
+        msg
+    );
+    assert!(encrypt_aes_128_ecb(&msg[1..], key, Padding::None, &mut rng).is_err());
+    assert!(encrypt_aes_128_ecb(msg, &key[1..], Padding::None, &mut rng).is_err());
+    let key_192 = [0; 24];
+    assert!(matches!(
+        encrypt_aes_128_ecb(msg, &key_192, Padding::None, &mut rng),
+        Err(Error::BadLength(_))
+    ));
+
+    let ciphertext = encrypt_aes_128_ecb(&msg[1..], key, Padding::Pkcs7, &mut rng).unwrap();
+    assert_eq!(ciphertext.len(), 2 * BLOCK_SIZE);
+    assert_eq!(
+        decrypt_aes_128_ecb(&ciphertext, key, Padding::Pkcs7).unwrap(),
+        &msg[1..]
+    );
+    assert!(matches!(
+        decrypt_aes_128_ecb(&ciphertext, key, Padding::Iso7816),
+        Err(Error::BadPadding)
+    ));
+}
+
+#[test]
+fn test_cbc_encrypt_decrypt() {
+    let mut rng = crate::random::seeded_rng(0);
+    let msg = b"YELLOW SUBMARINEYELLOW SUBMARINE";
+    let key = b"YELLOW SUBMARINE";
+    let iv = [0; BLOCK_SIZE];
+    let ciphertext = encrypt_aes_128_cbc(msg, &iv, key, Padding::None, &mut rng).unwrap();
+    assert_ne!(ciphertext[..BLOCK_SIZE], ciphertext[BLOCK_SIZE..]);
+    assert_eq!(
+        decrypt_aes_128_cbc(&ciphertext, &iv, key, Padding::None).unwrap(),
+        msg
+    );
+    assert!(decrypt_aes_128_cbc(&ciphertext, &iv[1..], key, Padding::None).is_err());
+    assert!(decrypt_aes_128_cbc(&ciphertext, &iv, &[0; 32], Padding::None).is_err());
+
+    let ciphertext = encrypt_aes_128_cbc(msg, &iv, key, Padding::AnsiX923, &mut rng).unwrap();
+    assert_eq!(ciphertext.len(), 3 * BLOCK_SIZE);
+    assert_eq!(
+        decrypt_aes_128_cbc(&ciphertext, &iv, key, Padding::AnsiX923).unwrap(),
+        msg
+    );
+
+    let seeded = |seed| {
+        let mut rng = crate::random::seeded_rng(seed);
+        encrypt_aes_128_cbc(msg, &iv, key, Padding::Iso10126, &mut rng).unwrap()
+    };
+    assert_eq!(seeded(5), seeded(5));
+    assert_ne!(seeded(5), seeded(6));
+}
+
+#[test]
+fn test_modes_with_8_byte_cipher() {
+    let cipher = XorRotate(42);
+    let msg = b"YELLOW SYELLOW S";
+    let ciphertext = encrypt_ecb(&cipher, msg).unwrap();
+    assert_eq!(ciphertext[..8], ciphertext[8..]);
+    assert_eq!(decrypt_ecb(&cipher, &ciphertext).unwrap(), msg);
+    let iv = [7; 8];
+    let ciphertext = encrypt_cbc(&cipher, msg, &iv).unwrap();
+    assert_ne!(ciphertext[..8], ciphertext[8..]);
+    assert_eq!(decrypt_cbc(&cipher, &ciphertext, &iv).unwrap(), msg);
+}
+
+#[cfg(test)]
+const SP_800_38A_IV: &str = "000102030405060708090a0b0c0d0e0f";
+
+#[cfg(test)]
+const SP_800_38A_PLAINTEXT: &str =
+    "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+    30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+#[test]
+fn test_cfb_sp_800_38a_vectors() {
+    use crate::encoding::{hex_decode, hex_encode};
+    let key = hex_decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+    let iv = hex_decode(SP_800_38A_IV).unwrap();
+    let plaintext = hex_decode(SP_800_38A_PLAINTEXT).unwrap();
+    // NIST SP 800-38A, F.3.1, F.3.7 and F.3.13
+    let vectors = [
+        (CfbSegment::Bit, 2, "68b3"),
+        (CfbSegment::Byte, 18, "3b79424c9c0dd436bace9e0ed4586a4f32b9"),
+        (
+            CfbSegment::Block,
+            64,
+            "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+             26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6",
+        ),
+    ];
+    for (segment, len, ciphertext) in vectors.iter() {
+        let encrypted = encrypt_aes_128_cfb(&plaintext[..*len], &iv, &key, *segment).unwrap();
+        assert_eq!(hex_encode(&encrypted), *ciphertext);
+        let decrypted = decrypt_aes_128_cfb(&encrypted, &iv, &key, *segment).unwrap();
+        assert_eq!(decrypted, &plaintext[..*len]);
+    }
+
+    // F.3.17, CFB-128 with AES-256
+    let aes = Aes::new(
+        &hex_decode("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4").unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        hex_encode(encrypt_cfb(&aes, &plaintext, &iv, CfbSegment::Block).unwrap()),
+        "dc7e84bfda79164b7ecd8486985d386039ffed143b28b1c832113c6331e5407b\
+         df10132415e54b92a13ed0a8267ae2f975a385741ab9cef82031623d55b1e471"
+    );
+}
+
+#[test]
+fn test_ofb_sp_800_38a_vectors() {
+    use crate::encoding::{hex_decode, hex_encode};
+    let iv = hex_decode(SP_800_38A_IV).unwrap();
+    let plaintext = hex_decode(SP_800_38A_PLAINTEXT).unwrap();
+    // NIST SP 800-38A, F.4.1
+    let key = hex_decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+    let encrypted = aes_128_ofb(&plaintext, &iv, &key).unwrap();
+    assert_eq!(
+        hex_encode(&encrypted),
+        "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+         9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e"
+    );
+    assert_eq!(aes_128_ofb(&encrypted, &iv, &key).unwrap(), plaintext);
+
+    // F.4.5, OFB with AES-256, which the AES-128 helpers reject
+    let key =
+        hex_decode("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4").unwrap();
+    assert!(matches!(
+        aes_128_ofb(&plaintext, &iv, &key),
+        Err(Error::BadLength(_))
+    ));
+    assert!(encrypt_aes_128_cfb(&plaintext, &iv, &key, CfbSegment::Block).is_err());
+    assert_eq!(
+        hex_encode(ofb(&Aes::new(&key).unwrap(), &plaintext, &iv).unwrap()),
+        "dc7e84bfda79164b7ecd8486985d38604febdc6740d20b3ac88f6ad82a4fb08d\
+         71ab47a086e86eedf39d1c5bba97c4080126141d67f37be8538f5a8be740e484"
+    );
+}
+
+#[test]
+fn test_partial_final_blocks() {
+    let cipher = XorRotate(42);
+    let msg = b"YELLOW SUBMARINE!";
+    let iv = [7; 8];
+    for segment in [CfbSegment::Bit, CfbSegment::Byte, CfbSegment::Block].iter() {
+        let ciphertext = encrypt_cfb(&cipher, msg, &iv, *segment).unwrap();
+        assert_eq!(ciphertext.len(), msg.len());
+        assert_eq!(
+            decrypt_cfb(&cipher, &ciphertext, &iv, *segment).unwrap(),
+            msg
+        );
+    }
+    let ciphertext = ofb(&cipher, msg, &iv).unwrap();
+    assert_eq!(ciphertext.len(), msg.len());
+    // The keystream does not depend on the message
+    assert_eq!(ofb(&cipher, &msg[..5], &iv).unwrap(), ciphertext[..5]);
+    assert_eq!(ofb(&cipher, &ciphertext, &iv).unwrap(), msg);
+    assert!(ofb(&cipher, msg, &iv[1..]).is_err());
+    assert!(encrypt_cfb(&cipher, msg, &[0; 16], CfbSegment::Byte).is_err());
+}
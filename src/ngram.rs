@@ -0,0 +1,242 @@
+//! Byte-level n-gram language models.
+//!
+//! A model is trained from a corpus into tables of conditional
+//! log-probabilities, for every n-gram length up to the order of the
+//! model, smoothed with Witten-Bell backoff to the shorter n-grams.
+//! A model trained on logs, source code or JSON can then rate
+//! plaintexts that aren't English prose.
+use crate::encoding::{hex_decode, hex_encode};
+use crate::error::{Error, Result};
+use crate::scoring::Scorer;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// First word of a serialized model
+const HEADER: &str = "ngram-model";
+
+/// Written in place of the empty context of unigrams
+const EMPTY_CONTEXT: &str = "-";
+
+/// ln P of any byte when nothing is known
+const UNIFORM_LOG_PROB: f32 = -5.545_177; // ln(1 / 256)
+
+#[derive(Debug, Clone)]
+pub struct NgramModel {
+    order: usize,
+    /// ln P(last byte | preceding bytes) of every n-gram seen in the corpus
+    log_probs: HashMap<Vec<u8>, f32>,
+    /// ln of the probability mass left to bytes never seen
+    /// after a context, for every context seen
+    unseen: HashMap<Vec<u8>, f32>,
+}
+
+impl NgramModel {
+    /// Train a model of the given order, e.g. 2 for bigrams
+    pub fn train(corpus: &[u8], order: usize) -> Result<NgramModel> {
+        if order == 0 {
+            return Err(Error::BadLength(
+                "Model order must be at least 1".to_string(),
+            ));
+        }
+        if corpus.is_empty() {
+            return Err(Error::BadLength("Training corpus is empty".to_string()));
+        }
+        let mut model = NgramModel {
+            order,
+            log_probs: HashMap::new(),
+            unseen: HashMap::new(),
+        };
+        // Lower orders first, as higher orders are smoothed with them
+        for n in 1..=order.min(corpus.len()) {
+            let mut counts: HashMap<&[u8], usize> = HashMap::new();
+            for gram in corpus.windows(n) {
+                *counts.entry(gram).or_insert(0) += 1;
+            }
+            // Occurrences and number of distinct bytes following each context
+            let mut contexts: HashMap<&[u8], (usize, usize)> = HashMap::new();
+            for (gram, &count) in counts.iter() {
+                let context = contexts.entry(&gram[..n - 1]).or_insert((0, 0));
+                context.0 += count;
+                context.1 += 1;
+            }
+            let mut log_probs = HashMap::new();
+            for (gram, &count) in counts.iter() {
+                let (total, types) = contexts[&gram[..n - 1]];
+                let lower = match n {
+                    1 => UNIFORM_LOG_PROB,
+                    _ => model.gram_log_prob(&gram[1..]),
+                };
+                let p = (count as f32 + types as f32 * lower.exp()) / (total + types) as f32;
+                log_probs.insert(gram.to_vec(), p.ln());
+            }
+            model.log_probs.extend(log_probs);
+            for (context, (total, types)) in contexts {
+                let weight = types as f32 / (total + types) as f32;
+                model.unseen.insert(context.to_vec(), weight.ln());
+            }
+        }
+        Ok(model)
+    }
+
+    /// Train a model from the contents of a file
+    pub fn train_file<P: AsRef<Path>>(path: P, order: usize) -> Result<NgramModel> {
+        NgramModel::train(&std::fs::read(path)?, order)
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// ln P of the last byte of the n-gram given the bytes before it.
+    /// N-grams missing from the model back off to the next shorter one,
+    /// weighted by the share of unseen bytes after their context.
+    fn gram_log_prob(&self, gram: &[u8]) -> f32 {
+        if let Some(&log_prob) = self.log_probs.get(gram) {
+            return log_prob;
+        }
+        let weight = self.unseen.get(&gram[..gram.len() - 1]).unwrap_or(&0.0);
+        let lower = match gram.len() {
+            1 => UNIFORM_LOG_PROB,
+            _ => self.gram_log_prob(&gram[1..]),
+        };
+        weight + lower
+    }
+
+    /// Write the model in a line based text format
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<()> {
+        writeln!(writer, "{} {}", HEADER, self.order)?;
+        let mut log_probs: Vec<_> = self.log_probs.iter().collect();
+        log_probs.sort_by_key(|(gram, _)| *gram);
+        for (gram, log_prob) in log_probs {
+            writeln!(writer, "p {} {}", hex_encode(gram), log_prob)?;
+        }
+        let mut unseen: Vec<_> = self.unseen.iter().collect();
+        unseen.sort_by_key(|(context, _)| *context);
+        for (context, log_prob) in unseen {
+            let context = match context.len() {
+                0 => EMPTY_CONTEXT.to_string(),
+                _ => hex_encode(context),
+            };
+            writeln!(writer, "u {} {}", context, log_prob)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.save(BufWriter::new(File::create(path)?))
+    }
+
+    /// Read a model written by `save`
+    pub fn load<R: BufRead>(reader: R) -> Result<NgramModel> {
+        let mut lines = reader.lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+        let order = match header.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [HEADER, order] => order.parse::<usize>().ok().filter(|&o| o > 0),
+            _ => None,
+        }
+        .ok_or_else(|| Error::InvalidEncoding(format!("Invalid model header: {}", header)))?;
+        let mut model = NgramModel {
+            order,
+            log_probs: HashMap::new(),
+            unseen: HashMap::new(),
+        };
+        for (number, line) in lines.enumerate() {
+            let line = line?;
+            let invalid =
+                || Error::InvalidEncoding(format!("Invalid model line {}: {}", number + 2, line));
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (kind, bytes, log_prob) = match fields.as_slice() {
+                [kind, bytes, log_prob] => (*kind, *bytes, *log_prob),
+                _ => return Err(invalid()),
+            };
+            let log_prob: f32 = log_prob.parse().map_err(|_| invalid())?;
+            let bytes = match bytes {
+                EMPTY_CONTEXT => Vec::new(),
+                _ => hex_decode(bytes)?,
+            };
+            match kind {
+                "p" if !bytes.is_empty() && bytes.len() <= order => {
+                    model.log_probs.insert(bytes, log_prob)
+                }
+                "u" if bytes.len() < order => model.unseen.insert(bytes, log_prob),
+                _ => return Err(invalid()),
+            };
+        }
+        Ok(model)
+    }
+
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<NgramModel> {
+        NgramModel::load(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Negative log-likelihood of the text under the model, averaged per byte
+impl Scorer for NgramModel {
+    fn score(&self, text: &[u8]) -> f32 {
+        if text.is_empty() {
+            return f32::MAX;
+        }
+        let total: f32 = (0..text.len())
+            .map(|i| {
+                let n = self.order.min(i + 1);
+                self.gram_log_prob(&text[i + 1 - n..=i])
+            })
+            .sum();
+        -total / text.len() as f32
+    }
+}
+
+#[cfg(test)]
+fn json_corpus() -> Vec<u8> {
+    let mut corpus = String::new();
+    for i in 0..200 {
+        corpus.push_str(&format!(
+            "{{\"id\": {}, \"level\": \"{}\", \"msg\": \"request {} done\"}}\n",
+            i,
+            ["info", "warn", "debug"][i % 3],
+            i * 7
+        ));
+    }
+    corpus.into_bytes()
+}
+
+#[test]
+fn test_ngram_scores_in_domain_text_first() {
+    let model = NgramModel::train(&json_corpus(), 3).unwrap();
+    let json = b"{\"id\": 3, \"level\": \"warn\"}";
+    let prose = b"Now that the party is jumping";
+    assert!(model.score(json) < model.score(prose));
+    assert!(model.score(b"zzzz") > model.score(b"info"));
+    assert!(NgramModel::train(b"abc", 0).is_err());
+    assert!(NgramModel::train(b"", 2).is_err());
+}
+
+#[test]
+fn test_ngram_breaks_json_xor() {
+    use crate::scoring::brute_force_with;
+    use crate::xor::single_byte_xor;
+    let model = NgramModel::train(&json_corpus(), 3).unwrap();
+    let plaintext = "{\"id\": 42, \"level\": \"debug\"}";
+    for key in 0..=255 {
+        let ciphertext = single_byte_xor(plaintext.as_bytes(), key);
+        assert_eq!(brute_force_with(&ciphertext, &model).text(), plaintext);
+    }
+}
+
+#[test]
+fn test_ngram_save_load() {
+    let model = NgramModel::train(&json_corpus(), 2).unwrap();
+    let mut saved = Vec::new();
+    model.save(&mut saved).unwrap();
+    let loaded = NgramModel::load(&saved[..]).unwrap();
+    assert_eq!(loaded.order(), 2);
+    for text in [&b"{\"id\": 1}"[..], b"hello", b"\x00\xff"].iter() {
+        assert!((model.score(text) - loaded.score(text)).abs() < 1e-4);
+    }
+    assert!(NgramModel::load(&b"ngram-model 0\n"[..]).is_err());
+    assert!(NgramModel::load(&b"ngram-model 2\np 616263 -1.0\n"[..]).is_err());
+    assert!(NgramModel::load(&b"ngram-model 2\np 61 nope\n"[..]).is_err());
+}
@@ -0,0 +1,178 @@
+//! Encryption oracles of set 2 and the attacks on them: telling ECB
+//! from CBC (challenge 11) and byte-at-a-time ECB decryption (challenge 12).
+use crate::detection::repeated_blocks;
+use crate::error::{Error, Result};
+use crate::modes::{aes_128, encrypt_aes_128_cbc, encrypt_aes_128_ecb, encrypt_ecb, BLOCK_SIZE};
+use crate::padding::{pkcs7, Padding};
+use crate::random::random_bytes;
+use rand::Rng;
+use std::collections::HashMap;
+
+/// Block cipher mode picked by the challenge 11 oracle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum CipherMode {
+    ECB,
+    CBC,
+}
+
+/// Surround the message with 1 to 5 random bytes on each side
+fn append_random_bytes<R: Rng + ?Sized>(rng: &mut R, mes: &[u8]) -> Vec<u8> {
+    let prefix_len = rng.gen_range(1, 6);
+    let suffix_len = rng.gen_range(1, 6);
+    let mut result = random_bytes(rng, prefix_len);
+    result.extend(mes);
+    result.extend(random_bytes(rng, suffix_len));
+    result
+}
+
+/// Pick ECB or CBC with equal probability
+pub fn random_mode<R: Rng + ?Sized>(rng: &mut R) -> CipherMode {
+    if rng.gen::<f64>() < 0.5 {
+        CipherMode::ECB
+    } else {
+        CipherMode::CBC
+    }
+}
+
+/// Encrypt the message surrounded by random bytes under
+/// a random key, and a random IV in CBC mode
+pub fn encryption_oracle<R: Rng + ?Sized>(
+    rng: &mut R,
+    mes: &[u8],
+    mode: CipherMode,
+) -> Result<Vec<u8>> {
+    let mes = append_random_bytes(rng, mes);
+    let key = random_bytes(rng, BLOCK_SIZE);
+    match mode {
+        CipherMode::ECB => encrypt_aes_128_ecb(&mes, &key, Padding::Pkcs7, rng),
+        CipherMode::CBC => {
+            let iv = random_bytes(rng, BLOCK_SIZE);
+            encrypt_aes_128_cbc(&mes, &iv, &key, Padding::Pkcs7, rng)
+        }
+    }
+}
+
+/// Guess the mode used by the oracle from repeated blocks
+pub fn detect_mode(ciphertext: &[u8]) -> Result<CipherMode> {
+    if repeated_blocks(ciphertext, BLOCK_SIZE)? {
+        Ok(CipherMode::ECB)
+    } else {
+        Ok(CipherMode::CBC)
+    }
+}
+
+/// Oracle of challenge 12, encrypting its input followed
+/// by a secret with AES-128 in ECB mode under a fixed key
+pub struct EcbOracle {
+    key: Vec<u8>,
+    secret: Vec<u8>,
+}
+
+impl EcbOracle {
+    pub fn new(key: &[u8], secret: &[u8]) -> EcbOracle {
+        EcbOracle {
+            key: key.to_vec(),
+            secret: secret.to_vec(),
+        }
+    }
+
+    pub fn encrypt(&self, mes: &[u8]) -> Result<Vec<u8>> {
+        let mut plaintext = mes.to_vec();
+        plaintext.extend(&self.secret);
+        encrypt_ecb(&aes_128(&self.key)?, &pkcs7(&plaintext, BLOCK_SIZE)?)
+    }
+}
+
+/// Detect the blocksize used by the ECB oracle.
+/// This doesn't make much sense, but it's
+/// what the challenge asks for
+pub fn detect_blocksize(oracle: &EcbOracle) -> Result<usize> {
+    for bs in 2..100 {
+        let mut mes = vec![42; bs * 2];
+        mes.push(3);
+        let ciphertext = oracle.encrypt(&mes)?;
+        if let Ok(true) = repeated_blocks(&ciphertext, bs) {
+            return Ok(bs);
+        }
+    }
+    Err(Error::AttackFailed(
+        "ECB or blocksize could not be detected".to_owned(),
+    ))
+}
+
+/// Builds a hash-map from the ciphertext block of every
+/// byte following the known plaintext to that byte
+fn build_dict(
+    oracle: &EcbOracle,
+    known_plaintext: &[u8],
+    blocksize: usize,
+) -> Result<HashMap<Vec<u8>, u8>> {
+    let mut dict: HashMap<Vec<u8>, u8> = HashMap::new();
+    for b in 0..=255 {
+        let mut mes = vec![42; blocksize * (known_plaintext.len() / blocksize + 1) - 1];
+        mes.drain(mes.len() - known_plaintext.len()..);
+        mes.extend(known_plaintext);
+        mes.push(b);
+        let step = known_plaintext.len() / blocksize * blocksize;
+        dict.insert(oracle.encrypt(&mes)?[step..step + blocksize].to_vec(), b);
+    }
+    Ok(dict)
+}
+
+/// Recover the secret of the oracle one byte at a time
+pub fn recover_secret(oracle: &EcbOracle, blocksize: usize) -> Result<Vec<u8>> {
+    let mut plaintext: Vec<u8> = Vec::new();
+    let oracle_length = oracle.encrypt(&[])?.len();
+    for i in 0..oracle_length {
+        let dict = build_dict(oracle, &plaintext, blocksize)?;
+        let mes = vec![42; blocksize - i % blocksize - 1];
+        let step = i / blocksize * blocksize;
+        let target_cipher = oracle.encrypt(&mes)?[step..step + blocksize].to_vec();
+        match dict.get(&target_cipher) {
+            Some(&byte) => plaintext.push(byte),
+            None => break,
+        }
+    }
+    // The last byte matched is the first byte of the padding,
+    // after which the padding changes and nothing matches
+    if plaintext.last() == Some(&1) {
+        plaintext.pop();
+    }
+    Ok(plaintext)
+}
+
+/// Break the ECB oracle, returning the detected blocksize and the secret
+pub fn break_ecb(oracle: &EcbOracle) -> Result<(usize, Vec<u8>)> {
+    let blocksize = detect_blocksize(oracle)?;
+    Ok((blocksize, recover_secret(oracle, blocksize)?))
+}
+
+#[test]
+fn test_oracle_is_reproducible() -> Result<()> {
+    use crate::random::seeded_rng;
+    let mes = [42; 16 * 4];
+    let a = encryption_oracle(&mut seeded_rng(7), &mes, CipherMode::CBC)?;
+    let b = encryption_oracle(&mut seeded_rng(7), &mes, CipherMode::CBC)?;
+    assert_eq!(a, b);
+    Ok(())
+}
+
+#[test]
+fn test_detect_mode() -> Result<()> {
+    let mut rng = crate::random::seeded_rng(11);
+    for _ in 0..100 {
+        let mode = random_mode(&mut rng);
+        let e = encryption_oracle(&mut rng, &[42; 16 * 4], mode)?;
+        assert_eq!(detect_mode(&e)?, mode);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_break_ecb() -> Result<()> {
+    let oracle = EcbOracle::new(b"YELLOW SUBMARINE", b"Rollin' in my 5.0\n");
+    assert_eq!(detect_blocksize(&oracle)?, 16);
+    assert_eq!(break_ecb(&oracle)?, (16, b"Rollin' in my 5.0\n".to_vec()));
+    Ok(())
+}
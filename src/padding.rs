@@ -0,0 +1,280 @@
+//! Block padding schemes.
+//!
+//! Every scheme pads up to the next multiple of the block size. All of
+//! them except zero padding always add at least one byte, so a message
+//! that is already aligned gets a full block of padding.
+//! Unpadding checks the padding and returns `Error::BadPadding` when it
+//! is malformed.
+use crate::error::{Error, Result};
+use crate::random::random_bytes;
+use rand::Rng;
+
+/// Padding scheme applied before encryption and removed after decryption
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    /// No padding, the message must already be made of whole blocks
+    None,
+    /// PKCS#7: every padding byte holds the padding length
+    Pkcs7,
+    /// ANSI X.923: zero bytes followed by the padding length
+    AnsiX923,
+    /// ISO 10126: random bytes followed by the padding length
+    Iso10126,
+    /// ISO/IEC 7816-4: a 0x80 byte followed by zero bytes
+    Iso7816,
+    /// Zero bytes, only added when the message is not aligned.
+    /// Trailing zero bytes of the message are lost when unpadding.
+    Zero,
+}
+
+impl Padding {
+    /// Pad a message up to a multiple of the block size.
+    /// ISO 10126 draws random bytes and needs `pad_with_rng` instead.
+    pub fn pad(self, message: &[u8], block_size: usize) -> Result<Vec<u8>> {
+        if self == Padding::Iso10126 {
+            return Err(Error::Crypto(
+                "ISO 10126 padding needs a random generator, see pad_with_rng".to_string(),
+            ));
+        }
+        // Only ISO 10126 asks for fill bytes, and it was rejected above
+        self.pad_inner(message, block_size, |_| Vec::new())
+    }
+
+    /// Pad a message, drawing the random bytes of ISO 10126 from `rng`
+    pub fn pad_with_rng<R: Rng + ?Sized>(
+        self,
+        message: &[u8],
+        block_size: usize,
+        rng: &mut R,
+    ) -> Result<Vec<u8>> {
+        self.pad_inner(message, block_size, |n| random_bytes(rng, n))
+    }
+
+    /// Pad a message, taking the ISO 10126 fill bytes from `fill`
+    fn pad_inner(
+        self,
+        message: &[u8],
+        block_size: usize,
+        fill: impl FnOnce(usize) -> Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        self.check_block_size(block_size)?;
+        let padding_len = block_size - (message.len() % block_size);
+        let mut message = message.to_vec();
+        match self {
+            Padding::None => {
+                check_blocks(&message, block_size)?;
+            }
+            Padding::Pkcs7 => message.extend(vec![padding_len as u8; padding_len]),
+            Padding::AnsiX923 => {
+                message.extend(vec![0; padding_len - 1]);
+                message.push(padding_len as u8);
+            }
+            Padding::Iso10126 => {
+                message.extend(fill(padding_len - 1));
+                message.push(padding_len as u8);
+            }
+            Padding::Iso7816 => {
+                message.push(0x80);
+                message.extend(vec![0; padding_len - 1]);
+            }
+            Padding::Zero => {
+                if padding_len != block_size {
+                    message.extend(vec![0; padding_len]);
+                }
+            }
+        }
+        Ok(message)
+    }
+
+    /// Check and remove the padding of a decrypted message
+    pub fn unpad(self, message: &[u8], block_size: usize) -> Result<Vec<u8>> {
+        self.check_block_size(block_size)?;
+        check_blocks(message, block_size)?;
+        let padding_len = match self {
+            Padding::None => 0,
+            Padding::Pkcs7 => {
+                let n = length_byte(message, block_size)?;
+                if message[message.len() - n..]
+                    .iter()
+                    .any(|&b| b as usize != n)
+                {
+                    return Err(Error::BadPadding);
+                }
+                n
+            }
+            Padding::AnsiX923 => {
+                let n = length_byte(message, block_size)?;
+                if message[message.len() - n..message.len() - 1]
+                    .iter()
+                    .any(|&b| b != 0)
+                {
+                    return Err(Error::BadPadding);
+                }
+                n
+            }
+            Padding::Iso10126 => length_byte(message, block_size)?,
+            Padding::Iso7816 => {
+                let zeros = trailing_zeros(message, block_size);
+                match message.len().checked_sub(zeros + 1) {
+                    Some(i) if zeros < block_size && message[i] == 0x80 => zeros + 1,
+                    _ => return Err(Error::BadPadding),
+                }
+            }
+            Padding::Zero => trailing_zeros(message, block_size - 1),
+        };
+        Ok(message[..message.len() - padding_len].to_vec())
+    }
+
+    /// The schemes storing the padding length in a byte
+    /// cannot describe more than 255 bytes of padding
+    fn check_block_size(self, block_size: usize) -> Result<()> {
+        let max = match self {
+            Padding::Pkcs7 | Padding::AnsiX923 | Padding::Iso10126 => 255,
+            _ => usize::MAX,
+        };
+        if block_size == 0 || block_size > max {
+            return Err(Error::BadLength(format!(
+                "{:?} block size must be between 1 and {}, got {}",
+                self, max, block_size
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Check that the message is made of whole blocks
+fn check_blocks(message: &[u8], block_size: usize) -> Result<()> {
+    if !message.len().is_multiple_of(block_size) {
+        return Err(Error::BadLength(format!(
+            "Message length {} is not a multiple of the blocksize {}",
+            message.len(),
+            block_size
+        )));
+    }
+    Ok(())
+}
+
+/// Read the padding length from the last byte of the message
+fn length_byte(message: &[u8], block_size: usize) -> Result<usize> {
+    let n = *message.last().ok_or(Error::BadPadding)? as usize;
+    if n == 0 || n > block_size {
+        return Err(Error::BadPadding);
+    }
+    Ok(n)
+}
+
+/// Count the zero bytes at the end of the message, up to max
+fn trailing_zeros(message: &[u8], max: usize) -> usize {
+    message
+        .iter()
+        .rev()
+        .take(max)
+        .take_while(|&&b| b == 0)
+        .count()
+}
+
+/// Pad a message, given a blocksize
+pub fn pkcs7(message: &[u8], block_size: usize) -> Result<Vec<u8>> {
+    Padding::Pkcs7.pad(message, block_size)
+}
+
+/// Check and remove the PKCS#7 padding of a message
+pub fn pkcs7_unpad(message: &[u8], block_size: usize) -> Result<Vec<u8>> {
+    Padding::Pkcs7.unpad(message, block_size)
+}
+
+#[test]
+fn test_pkcs7() {
+    assert_eq!(
+        pkcs7(b"YELLOW SUBMARINE", 20).unwrap().as_slice(),
+        b"YELLOW SUBMARINE\x04\x04\x04\x04"
+    );
+    assert_eq!(
+        pkcs7(b"YELLOW SUBMARINE", 16).unwrap(),
+        [b"YELLOW SUBMARINE" as &[u8], &[16; 16]].concat()
+    );
+    assert!(pkcs7(b"YELLOW SUBMARINE", 0).is_err());
+    assert!(pkcs7(b"YELLOW SUBMARINE", 256).is_err());
+}
+
+#[test]
+fn test_pkcs7_unpad() {
+    assert_eq!(
+        pkcs7_unpad(b"ICE ICE BABY\x04\x04\x04\x04", 16).unwrap(),
+        b"ICE ICE BABY"
+    );
+    for bad in [
+        &b"ICE ICE BABY\x05\x05\x05\x05"[..],
+        b"ICE ICE BABY\x01\x02\x03\x04",
+        b"ICE ICE BABY\x00\x00\x00\x00",
+        b"ICE ICE BABY\x04\x04\x04\x11",
+    ]
+    .iter()
+    {
+        match pkcs7_unpad(bad, 16) {
+            Err(Error::BadPadding) => {}
+            other => panic!("expected bad padding, got {:?}", other),
+        }
+    }
+    assert!(matches!(
+        pkcs7_unpad(b"ICE ICE BABY\x04\x04\x04", 16),
+        Err(Error::BadLength(_))
+    ));
+}
+
+#[test]
+fn test_padding_schemes() {
+    let msg = b"YELLOW SUBMARINE!";
+    assert_eq!(
+        Padding::AnsiX923.pad(b"YELLOW", 8).unwrap(),
+        b"YELLOW\x00\x02"
+    );
+    assert_eq!(
+        Padding::Iso7816.pad(b"YELLOW", 8).unwrap(),
+        b"YELLOW\x80\x00"
+    );
+    assert_eq!(Padding::Zero.pad(b"YELLOW", 8).unwrap(), b"YELLOW\x00\x00");
+    assert_eq!(Padding::Zero.pad(b"YELLOWSU", 8).unwrap(), b"YELLOWSU");
+    assert!(Padding::None.pad(b"YELLOW", 8).is_err());
+    assert!(matches!(
+        Padding::Iso10126.pad(b"YELLOW", 8),
+        Err(Error::Crypto(_))
+    ));
+    let mut rng = crate::random::seeded_rng(9);
+    assert_eq!(
+        Padding::Iso10126
+            .pad_with_rng(b"YELLOW", 16, &mut rng)
+            .unwrap(),
+        Padding::Iso10126
+            .pad_with_rng(b"YELLOW", 16, &mut crate::random::seeded_rng(9))
+            .unwrap()
+    );
+    let schemes = [
+        Padding::Pkcs7,
+        Padding::AnsiX923,
+        Padding::Iso10126,
+        Padding::Iso7816,
+        Padding::Zero,
+    ];
+    for scheme in schemes.iter() {
+        for len in 0..=msg.len() {
+            let padded = scheme.pad_with_rng(&msg[..len], 16, &mut rng).unwrap();
+            assert!(padded.len().is_multiple_of(16) && padded.len() >= len);
+            assert_eq!(scheme.unpad(&padded, 16).unwrap(), &msg[..len]);
+        }
+    }
+    let bad_x923 = b"YELLOW\x00\x01\x00\x03";
+    assert!(matches!(
+        Padding::AnsiX923.unpad(bad_x923, 10),
+        Err(Error::BadPadding)
+    ));
+    assert!(Padding::Iso10126.unpad(bad_x923, 10).is_ok());
+    assert!(matches!(
+        Padding::Iso7816.unpad(b"YELLOW\x00\x00", 8),
+        Err(Error::BadPadding)
+    ));
+    assert!(matches!(
+        Padding::Iso7816.unpad(&[0; 8], 8),
+        Err(Error::BadPadding)
+    ));
+}
@@ -0,0 +1,398 @@
+//! Letter frequency profiles of several languages.
+//!
+//! A `ProfileRegistry` holds the built-in profiles and any custom ones
+//! loaded from a file. Used as a `Scorer`, it rates a text against every
+//! profile and keeps the best fit, so the language of the plaintext
+//! doesn't need to be known in advance.
+//!
+//! Profiles describe UTF-8 text.
+use crate::error::{Error, Result};
+use crate::scoring::{log_likelihood, ByteFrequencies, Scorer};
+use std::path::Path;
+
+/// Letter frequencies, in percent of all letters. Source:
+/// https://en.wikipedia.org/wiki/Letter_frequency
+const SPANISH: [(char, f32); 33] = [
+    ('a', 11.525),
+    ('b', 2.215),
+    ('c', 4.019),
+    ('d', 5.010),
+    ('e', 12.181),
+    ('f', 0.692),
+    ('g', 1.768),
+    ('h', 0.703),
+    ('i', 6.247),
+    ('j', 0.493),
+    ('k', 0.011),
+    ('l', 4.967),
+    ('m', 3.157),
+    ('n', 6.712),
+    ('o', 8.683),
+    ('p', 2.510),
+    ('q', 0.877),
+    ('r', 6.871),
+    ('s', 7.977),
+    ('t', 4.632),
+    ('u', 2.927),
+    ('v', 1.138),
+    ('w', 0.017),
+    ('x', 0.215),
+    ('y', 1.008),
+    ('z', 0.467),
+    ('á', 0.502),
+    ('é', 0.433),
+    ('í', 0.725),
+    ('ñ', 0.311),
+    ('ó', 0.827),
+    ('ú', 0.168),
+    ('ü', 0.012),
+];
+
+const GERMAN: [(char, f32); 30] = [
+    ('a', 6.516),
+    ('b', 1.886),
+    ('c', 2.732),
+    ('d', 5.076),
+    ('e', 16.396),
+    ('f', 1.656),
+    ('g', 3.009),
+    ('h', 4.577),
+    ('i', 6.550),
+    ('j', 0.268),
+    ('k', 1.417),
+    ('l', 3.437),
+    ('m', 2.534),
+    ('n', 9.776),
+    ('o', 2.594),
+    ('p', 0.670),
+    ('q', 0.018),
+    ('r', 7.003),
+    ('s', 7.270),
+    ('t', 6.154),
+    ('u', 4.166),
+    ('v', 0.846),
+    ('w', 1.921),
+    ('x', 0.034),
+    ('y', 0.039),
+    ('z', 1.134),
+    ('ä', 0.578),
+    ('ö', 0.443),
+    ('ß', 0.307),
+    ('ü', 0.995),
+];
+
+const FRENCH: [(char, f32); 39] = [
+    ('a', 7.636),
+    ('b', 0.901),
+    ('c', 3.260),
+    ('d', 3.669),
+    ('e', 14.715),
+    ('f', 1.066),
+    ('g', 0.866),
+    ('h', 0.737),
+    ('i', 7.529),
+    ('j', 0.613),
+    ('k', 0.074),
+    ('l', 5.456),
+    ('m', 2.968),
+    ('n', 7.095),
+    ('o', 5.796),
+    ('p', 2.521),
+    ('q', 1.362),
+    ('r', 6.693),
+    ('s', 7.948),
+    ('t', 7.244),
+    ('u', 6.311),
+    ('v', 1.838),
+    ('w', 0.049),
+    ('x', 0.427),
+    ('y', 0.128),
+    ('z', 0.326),
+    ('à', 0.486),
+    ('â', 0.051),
+    ('œ', 0.018),
+    ('ç', 0.085),
+    ('è', 0.271),
+    ('é', 1.504),
+    ('ê', 0.218),
+    ('ë', 0.008),
+    ('î', 0.045),
+    ('ï', 0.005),
+    ('ô', 0.023),
+    ('ù', 0.058),
+    ('û', 0.060),
+];
+
+const ITALIAN: [(char, f32); 31] = [
+    ('a', 11.745),
+    ('b', 0.927),
+    ('c', 4.501),
+    ('d', 3.736),
+    ('e', 11.792),
+    ('f', 1.153),
+    ('g', 1.644),
+    ('h', 0.636),
+    ('i', 10.143),
+    ('j', 0.011),
+    ('k', 0.009),
+    ('l', 6.510),
+    ('m', 2.512),
+    ('n', 6.883),
+    ('o', 9.832),
+    ('p', 3.056),
+    ('q', 0.505),
+    ('r', 6.367),
+    ('s', 4.981),
+    ('t', 5.623),
+    ('u', 3.011),
+    ('v', 2.097),
+    ('w', 0.033),
+    ('x', 0.003),
+    ('y', 0.020),
+    ('z', 1.181),
+    ('à', 0.635),
+    ('è', 0.263),
+    ('ì', 0.030),
+    ('ò', 0.002),
+    ('ù', 0.166),
+];
+
+const PORTUGUESE: [(char, f32); 39] = [
+    ('a', 14.634),
+    ('b', 1.043),
+    ('c', 3.882),
+    ('d', 4.992),
+    ('e', 12.570),
+    ('f', 1.023),
+    ('g', 1.303),
+    ('h', 0.781),
+    ('i', 6.186),
+    ('j', 0.397),
+    ('k', 0.015),
+    ('l', 2.779),
+    ('m', 4.738),
+    ('n', 4.446),
+    ('o', 9.735),
+    ('p', 2.523),
+    ('q', 1.204),
+    ('r', 6.530),
+    ('s', 6.805),
+    ('t', 4.336),
+    ('u', 3.639),
+    ('v', 1.575),
+    ('w', 0.037),
+    ('x', 0.253),
+    ('y', 0.006),
+    ('z', 0.470),
+    ('à', 0.072),
+    ('â', 0.562),
+    ('á', 0.118),
+    ('ã', 0.733),
+    ('ç', 0.530),
+    ('é', 0.337),
+    ('ê', 0.450),
+    ('í', 0.132),
+    ('ó', 0.296),
+    ('ô', 0.635),
+    ('õ', 0.040),
+    ('ú', 0.207),
+    ('ü', 0.026),
+];
+
+/// Byte frequencies of a language
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub frequencies: ByteFrequencies,
+}
+
+impl Profile {
+    pub fn new(name: &str, frequencies: ByteFrequencies) -> Profile {
+        Profile {
+            name: name.to_string(),
+            frequencies,
+        }
+    }
+
+    /// Read a profile from a file of letter frequencies. Each line holds
+    /// a lowercase letter and its frequency in any unit, e.g. `e 12.7`.
+    /// A `name <name>` line sets the name, the file stem is used otherwise.
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Profile> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        let mut name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let mut letters = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid =
+                || Error::InvalidEncoding(format!("Invalid profile line {}: {}", number + 1, line));
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["name", value] => name = value.to_lowercase(),
+                [letter, frequency] => {
+                    let mut chars = letter.chars();
+                    let c = match (chars.next(), chars.next()) {
+                        (Some(c), None) => c,
+                        _ => return Err(invalid()),
+                    };
+                    let frequency: f32 = frequency.parse().map_err(|_| invalid())?;
+                    if !(frequency >= 0.0 && frequency.is_finite()) {
+                        return Err(invalid());
+                    }
+                    letters.push((c, frequency));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        if name.is_empty() || letters.iter().all(|&(_, f)| f == 0.0) {
+            return Err(Error::InvalidEncoding(format!(
+                "Profile {} needs a name and some letter frequencies",
+                path.display()
+            )));
+        }
+        Ok(Profile::new(&name, ByteFrequencies::from_letters(&letters)))
+    }
+}
+
+/// Negative log-likelihood of the text in the language of the profile
+impl Scorer for Profile {
+    fn score(&self, text: &[u8]) -> f32 {
+        log_likelihood(text, &self.frequencies)
+    }
+}
+
+/// A set of language profiles, looked up by name
+#[derive(Debug, Clone, Default)]
+pub struct ProfileRegistry {
+    profiles: Vec<Profile>,
+}
+
+impl ProfileRegistry {
+    pub fn new() -> ProfileRegistry {
+        ProfileRegistry::default()
+    }
+
+    /// English, Spanish, German, French, Italian and Portuguese
+    pub fn builtin() -> ProfileRegistry {
+        let mut registry = ProfileRegistry::new();
+        registry.register(Profile::new("english", ByteFrequencies::english()));
+        let languages: [(&str, &[(char, f32)]); 5] = [
+            ("spanish", &SPANISH),
+            ("german", &GERMAN),
+            ("french", &FRENCH),
+            ("italian", &ITALIAN),
+            ("portuguese", &PORTUGUESE),
+        ];
+        for (name, letters) in languages.iter() {
+            registry.register(Profile::new(name, ByteFrequencies::from_letters(letters)));
+        }
+        registry
+    }
+
+    /// Add a profile, replacing any profile of the same name
+    pub fn register(&mut self, profile: Profile) {
+        self.profiles.retain(|p| p.name != profile.name);
+        self.profiles.push(profile);
+    }
+
+    /// Add a profile read from a file, see `Profile::from_file`
+    pub fn register_file<P: AsRef<Path>>(&mut self, path: P) -> Result<&Profile> {
+        self.register(Profile::from_file(path)?);
+        Ok(&self.profiles[self.profiles.len() - 1])
+    }
+
+    /// Look a profile up by name, ignoring case
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        let name = name.to_lowercase();
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    pub fn profiles(&self) -> &[Profile] {
+        &self.profiles
+    }
+
+    /// The profile the text fits best, if there are any profiles
+    pub fn best_fit(&self, text: &[u8]) -> Option<&Profile> {
+        self.profiles
+            .iter()
+            .map(|p| (p.score(text), p))
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(_, p)| p)
+    }
+}
+
+/// Score of the text under the profile it fits best
+impl Scorer for ProfileRegistry {
+    fn score(&self, text: &[u8]) -> f32 {
+        self.profiles
+            .iter()
+            .map(|p| p.score(text))
+            .fold(f32::MAX, f32::min)
+    }
+}
+
+#[test]
+fn test_best_fit() {
+    let registry = ProfileRegistry::builtin();
+    let samples = [
+        ("english", "The quick brown fox jumps over the lazy dog while the farmer watches from the field."),
+        ("spanish", "El señor García compró una canción antigua en la tienda de música del pueblo, según dijo su hijo."),
+        ("german", "Der größte Bär des Waldes schläft über den Winter in einer Höhle, während draußen der Schnee fällt."),
+        ("french", "Le garçon a déjà mangé les crêpes préparées par sa grand-mère, puis il est allé à l'école."),
+        ("italian", "Il ragazzo mangia la pizza con gli amici nella piazza della città vecchia, vicino alla chiesa."),
+        ("portuguese", "A população não está satisfeita com as condições das estações, mas a região também cresce."),
+    ];
+    for (language, text) in samples.iter() {
+        assert_eq!(&registry.best_fit(text.as_bytes()).unwrap().name, language);
+    }
+    assert!(ProfileRegistry::new().best_fit(b"anything").is_none());
+    assert!(registry.get("German").is_some());
+}
+
+#[test]
+fn test_registry_breaks_accented_xor() {
+    use crate::scoring::brute_force_with;
+    use crate::xor::single_byte_xor;
+    let registry = ProfileRegistry::builtin();
+    let plaintext = "Größe über Bär, schön.";
+    for key in 0..=255 {
+        let ciphertext = single_byte_xor(plaintext.as_bytes(), key);
+        let candidate = brute_force_with(&ciphertext, &registry);
+        assert_eq!(candidate.text(), plaintext, "key {}", key);
+    }
+}
+
+#[test]
+fn test_profile_from_file() {
+    let dir = std::env::temp_dir().join(format!("profiles-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("Dutch.txt");
+    std::fs::write(
+        &path,
+        "# Dutch letters\ne 18.91\nn 10.03\na 7.49\nt 6.79\ni 6.50\nr 6.41\no 6.06\n\
+         d 5.93\ns 3.73\nl 3.57\ng 3.40\nv 2.85\nh 2.38\nk 2.25\nm 2.21\nu 1.99\n\
+         b 1.58\np 1.57\nw 1.52\nj 1.46\nz 1.39\nc 1.24\nf 0.81\nij 1.0\n",
+    )
+    .unwrap();
+    let mut registry = ProfileRegistry::builtin();
+    assert!(registry.register_file(&path).is_err());
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, contents.replace("ij 1.0\n", "")).unwrap();
+    assert_eq!(registry.register_file(&path).unwrap().name, "dutch");
+    let text = "De kinderen spelen in de tuin van hun grootouders en eten veel druiven.";
+    assert_eq!(registry.best_fit(text.as_bytes()).unwrap().name, "dutch");
+
+    std::fs::write(&path, "name nederlands\ne 18.91\n").unwrap();
+    assert_eq!(registry.register_file(&path).unwrap().name, "nederlands");
+    std::fs::write(&path, "e lots\n").unwrap();
+    assert!(registry.register_file(&path).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,24 @@
+//! Randomness helpers.
+//!
+//! Code needing randomness takes the generator as a parameter, so a
+//! run can be replayed exactly by passing a generator from `seeded_rng`.
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Generator seeded with a fixed value, for reproducible runs
+pub fn seeded_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// Generate random bytes, e.g. for a key or an IV
+pub fn random_bytes<R: Rng + ?Sized>(rng: &mut R, len: usize) -> Vec<u8> {
+    (0..len).map(|_| rng.gen()).collect()
+}
+
+#[test]
+fn test_seeded_rng() {
+    let a = random_bytes(&mut seeded_rng(42), 16);
+    assert_eq!(a.len(), 16);
+    assert_eq!(a, random_bytes(&mut seeded_rng(42), 16));
+    assert_ne!(a, random_bytes(&mut seeded_rng(43), 16));
+}
@@ -0,0 +1,537 @@
+//! Breaking repeating-key XOR by transposing the ciphertext
+//! into one single-byte XOR problem per key byte.
+use crate::bits;
+use crate::error::{Error, Result};
+use crate::scoring::{byte_histogram, rank_candidates, Candidate, LogLikelihood, Scorer};
+use crate::xor::repeating_key_xor;
+
+/// Number of best key bytes kept for each block
+const CANDIDATES_PER_BLOCK: usize = 5;
+
+/// A block is ambiguous when its runner-up scores
+/// within this fraction of its best candidate
+const AMBIGUITY: f32 = 0.1;
+
+/// Bytes of context on each side of a byte when trying
+/// the runners-up of a block, enough for trigram models
+pub(crate) const CONTEXT: usize = 2;
+
+/// Number of rounds over the ambiguous blocks
+const REFINE_PASSES: usize = 2;
+
+/// Number of leading blocks compared pairwise by `average_distance`
+const MAX_BLOCKS: usize = 32;
+
+/// Plaintexts scoring within this fraction of the best one are
+/// considered as good, and the shortest key among them is kept, since
+/// multiples of the key size always fit the ciphertext at least as well
+const SIZE_TOLERANCE: f32 = 0.02;
+
+/// Statistic used to rank key sizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySizeMethod {
+    /// Normalized Hamming distance between all pairs of blocks,
+    /// lowest for the right key size
+    Hamming,
+    /// Index of coincidence of the transposed blocks,
+    /// highest for the right key size
+    IndexOfCoincidence,
+}
+
+/// Settings of the key size search
+#[derive(Debug, Clone)]
+pub struct KeySizeOptions {
+    /// Smallest key size tried
+    pub min_size: usize,
+    /// Largest key size tried, capped at half the ciphertext length
+    pub max_size: usize,
+    pub method: KeySizeMethod,
+    /// Number of best key sizes fully decrypted,
+    /// keeping the one whose plaintext scores best
+    pub trials: usize,
+}
+
+impl Default for KeySizeOptions {
+    fn default() -> KeySizeOptions {
+        KeySizeOptions {
+            min_size: 2,
+            max_size: 40,
+            method: KeySizeMethod::Hamming,
+            trials: 3,
+        }
+    }
+}
+
+/// Key and plaintext recovered from a repeating-key XOR ciphertext
+#[derive(Debug, Clone)]
+pub struct Solution {
+    pub key: Vec<u8>,
+    /// Confidence in each key byte, from 0 when its best candidate
+    /// ties with the runner-up to 1 when it has no contender
+    pub confidence: Vec<f32>,
+    pub plaintext: Vec<u8>,
+}
+
+impl Solution {
+    pub fn key_size(&self) -> usize {
+        self.key.len()
+    }
+
+    /// Decrypt another ciphertext encrypted under the recovered key
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        repeating_key_xor(ciphertext, &self.key)
+    }
+}
+
+/// Ciphertext transposed into `key_size` blocks, where block `i`
+/// holds every byte encrypted with key byte `i`
+pub struct Blocks {
+    pub matrix: Vec<Vec<u8>>,
+    pub vec_size: usize,
+    pub key_size: usize,
+}
+
+impl Blocks {
+    pub fn new(v: Vec<u8>, key_size: usize) -> Result<Blocks> {
+        if key_size == 0 {
+            return Err(Error::BadLength("Key size must be at least 1".to_string()));
+        }
+        let vec_size = v.len();
+        let block_len = vec_size / key_size + 1;
+        let mut matrix: Vec<Vec<u8>> = Vec::with_capacity(key_size);
+        for i in 0..key_size {
+            let mut block: Vec<u8> = Vec::with_capacity(block_len);
+            for j in 0..block_len {
+                let index = key_size * j + i;
+                if index < vec_size {
+                    block.push(v[index]);
+                }
+            }
+            matrix.push(block)
+        }
+        Ok(Blocks {
+            matrix,
+            vec_size,
+            key_size,
+        })
+    }
+
+    /// Solve every block as single-byte XOR and
+    /// interleave the results back into a plaintext
+    pub fn solve(&self) -> Solution {
+        self.solve_with(&LogLikelihood::default())
+    }
+
+    /// Same as `solve`, rating the candidates of each block with `scorer`.
+    /// The bytes of a block are `key_size` apart in the plaintext, so when
+    /// the best key bytes of a block score too close to call, the runners-up
+    /// are tried again with the neighbouring plaintext bytes in view,
+    /// which also lets n-gram models see real n-grams.
+    pub fn solve_with<S: Scorer + ?Sized>(&self, scorer: &S) -> Solution {
+        let mut plaintext = vec![0; self.vec_size];
+        let mut text = Interleaved {
+            blocks: self,
+            plaintext: &mut plaintext,
+        };
+        let (key, confidence) = solve_key_bytes(&mut text, &self.matrix, scorer);
+        Solution {
+            key,
+            confidence,
+            plaintext,
+        }
+    }
+
+    /// Write the bytes of a block decrypted with a key byte into the plaintext
+    fn decrypt_block(&self, plaintext: &mut [u8], block_num: usize, key: u8) {
+        for (i, &b) in self.matrix[block_num].iter().enumerate() {
+            plaintext[block_num + self.key_size * i] = b ^ key;
+        }
+    }
+}
+
+/// Plaintext of a repeating-key XOR ciphertext, key byte `i`
+/// covering the bytes of block `i`
+struct Interleaved<'a> {
+    blocks: &'a Blocks,
+    plaintext: &'a mut [u8],
+}
+
+impl KeyedText for Interleaved<'_> {
+    fn decrypt(&mut self, i: usize, key: u8) {
+        self.blocks.decrypt_block(self.plaintext, i, key);
+    }
+
+    fn windows(&self, i: usize, windows: &mut Vec<u8>) {
+        for j in 0..self.blocks.matrix[i].len() {
+            let index = i + self.blocks.key_size * j;
+            let start = index.saturating_sub(CONTEXT);
+            let end = (index + CONTEXT + 1).min(self.blocks.vec_size);
+            windows.extend_from_slice(&self.plaintext[start..end]);
+        }
+    }
+}
+
+/// Plaintext recovered one key byte at a time by `solve_key_bytes`
+pub(crate) trait KeyedText {
+    /// Decrypt the bytes under key byte `i` with `key`
+    fn decrypt(&mut self, i: usize, key: u8);
+    /// Append the plaintext around the bytes under key byte `i` to `windows`
+    fn windows(&self, i: usize, windows: &mut Vec<u8>);
+}
+
+/// Solve the bytes under each key byte as single-byte XOR, `blocks[i]`
+/// being the bytes under key byte `i`, and decrypt `text` with the result.
+/// Key bytes whose best candidates are too close to call are picked again
+/// over a few rounds, scoring their candidates on the windows of `text`.
+/// Returns the key along with the confidence in each of its bytes.
+pub(crate) fn solve_key_bytes<T, B, S>(
+    text: &mut T,
+    blocks: &[B],
+    scorer: &S,
+) -> (Vec<u8>, Vec<f32>)
+where
+    T: KeyedText + ?Sized,
+    B: AsRef<[u8]>,
+    S: Scorer + ?Sized,
+{
+    let ranked: Vec<Vec<Candidate>> = blocks
+        .iter()
+        .map(|block| rank_candidates(block.as_ref(), scorer, CANDIDATES_PER_BLOCK))
+        .collect();
+    let mut key = vec![0; blocks.len()];
+    let mut confidences = vec![0.0; blocks.len()];
+    for (i, candidates) in ranked.iter().enumerate() {
+        if let Some(best) = candidates.first() {
+            text.decrypt(i, best.key);
+            key[i] = best.key;
+            confidences[i] = confidence(best.score, candidates.get(1).map(|c| c.score));
+        }
+    }
+    for _ in 0..REFINE_PASSES {
+        for (i, candidates) in ranked.iter().enumerate() {
+            if is_ambiguous(candidates) {
+                (key[i], confidences[i]) = refine_key_byte(text, i, candidates, scorer);
+            }
+        }
+    }
+    (key, confidences)
+}
+
+/// Pick key byte `i` among its candidates again, scoring each on the
+/// windows of plaintext around its bytes. Returns the key byte along
+/// with the confidence in it.
+fn refine_key_byte<T: KeyedText + ?Sized, S: Scorer + ?Sized>(
+    text: &mut T,
+    i: usize,
+    candidates: &[Candidate],
+    scorer: &S,
+) -> (u8, f32) {
+    let mut best_score = f32::MAX;
+    let mut runner_up_score = f32::MAX;
+    let mut best_key = candidates[0].key;
+    let mut windows = Vec::new();
+    for candidate in candidates {
+        text.decrypt(i, candidate.key);
+        windows.clear();
+        text.windows(i, &mut windows);
+        let score = scorer.score(&windows);
+        if score < best_score {
+            runner_up_score = best_score;
+            best_score = score;
+            best_key = candidate.key;
+        } else if score < runner_up_score {
+            runner_up_score = score;
+        }
+    }
+    text.decrypt(i, best_key);
+    (best_key, confidence(best_score, Some(runner_up_score)))
+}
+
+/// Relative margin between the best score and the runner-up, from 0 to 1
+pub(crate) fn confidence(best: f32, runner_up: Option<f32>) -> f32 {
+    match runner_up {
+        Some(runner_up) if runner_up < f32::MAX && runner_up > 0.0 => {
+            ((runner_up - best) / runner_up).clamp(0.0, 1.0)
+        }
+        _ => 1.0,
+    }
+}
+
+/// Whether the runner-up of a block scores too close to its best candidate
+fn is_ambiguous(candidates: &[Candidate]) -> bool {
+    match candidates {
+        [best, runner_up, ..] => runner_up.score - best.score <= AMBIGUITY * best.score.abs(),
+        _ => false,
+    }
+}
+
+/// Number of differing bits between two byte slices, see `bits::distance`
+pub fn hamming(x: &[u8], y: &[u8]) -> Result<u64> {
+    bits::distance(x, y)
+}
+
+/// Check that there are at least two blocks of length n in v
+fn check_key_size(v: &[u8], n: usize) -> Result<()> {
+    if n == 0 || v.len() < 2 * n {
+        return Err(Error::BadLength(format!(
+            "Key size {} needs a ciphertext of at least two blocks, got {} bytes",
+            n,
+            v.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Normalized average hamming distance between all pairs
+/// of blocks of length n among the first blocks of vector v
+pub fn average_distance(v: &[u8], n: usize) -> Result<f64> {
+    check_key_size(v, n)?;
+    let blocks: Vec<&[u8]> = v.chunks_exact(n).take(MAX_BLOCKS).collect();
+    let mut sum_distance = 0_f64;
+    let mut pairs = 0;
+    for (i, x) in blocks.iter().enumerate() {
+        for y in &blocks[i + 1..] {
+            sum_distance += hamming(x, y)? as f64;
+            pairs += 1;
+        }
+    }
+    Ok(sum_distance / pairs as f64 / n as f64)
+}
+
+/// Average index of coincidence of the blocks of bytes
+/// encrypted with the same key byte, for a key of length n
+pub fn index_of_coincidence(v: &[u8], n: usize) -> Result<f64> {
+    check_key_size(v, n)?;
+    let blocks = Blocks::new(v.to_vec(), n)?;
+    let mut sum = 0_f64;
+    for block in &blocks.matrix {
+        let len = block.len() as f64;
+        let coincidences: usize = byte_histogram(block)
+            .iter()
+            .map(|&count| count * count.saturating_sub(1))
+            .sum();
+        sum += coincidences as f64 / (len * (len - 1.0));
+    }
+    Ok(sum / n as f64)
+}
+
+/// Rate every key size in the range of the options,
+/// returning the sizes and their statistic, best first
+pub fn estimate_key_sizes(v: &[u8], options: &KeySizeOptions) -> Result<Vec<(usize, f64)>> {
+    let max_size = options.max_size.min(v.len() / 2);
+    if options.min_size == 0 || options.min_size > max_size {
+        return Err(Error::BadLength(format!(
+            "No key size between {} and {} fits a ciphertext of {} bytes",
+            options.min_size,
+            options.max_size,
+            v.len()
+        )));
+    }
+    let mut sizes = Vec::with_capacity(max_size - options.min_size + 1);
+    for key_size in options.min_size..=max_size {
+        let statistic = match options.method {
+            KeySizeMethod::Hamming => average_distance(v, key_size)?,
+            KeySizeMethod::IndexOfCoincidence => -index_of_coincidence(v, key_size)?,
+        };
+        sizes.push((key_size, statistic));
+    }
+    sizes.sort_by(|a, b| {
+        (a.1, a.0)
+            .partial_cmp(&(b.1, b.0))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    if options.method == KeySizeMethod::IndexOfCoincidence {
+        sizes.iter_mut().for_each(|size| size.1 = -size.1);
+    }
+    Ok(sizes)
+}
+
+/// Shorten a key made of a shorter key repeated, keeping
+/// the lowest confidence of the bytes that are merged
+pub(crate) fn reduce_key(solution: &mut Solution) {
+    let len = solution.key.len();
+    let period = (1..len)
+        .filter(|p| len.is_multiple_of(*p))
+        .find(|&p| (p..len).all(|i| solution.key[i] == solution.key[i - p]));
+    if let Some(period) = period {
+        for i in period..len {
+            let merged = solution.confidence[i % period].min(solution.confidence[i]);
+            solution.confidence[i % period] = merged;
+        }
+        solution.key.truncate(period);
+        solution.confidence.truncate(period);
+    }
+}
+
+/// Guess the key size of a repeating-key XOR ciphertext
+/// and recover the key and the plaintext
+pub fn break_repeating_key_xor(cipherbytes: Vec<u8>) -> Result<Solution> {
+    break_repeating_key_xor_with(cipherbytes, &LogLikelihood::default())
+}
+
+/// Same as `break_repeating_key_xor`, rating plaintexts with `scorer`
+pub fn break_repeating_key_xor_with<S: Scorer + ?Sized>(
+    cipherbytes: Vec<u8>,
+    scorer: &S,
+) -> Result<Solution> {
+    break_repeating_key_xor_with_options(cipherbytes, scorer, &KeySizeOptions::default())
+}
+
+/// Same as `break_repeating_key_xor_with`, trying the key sizes
+/// the options describe. Each of the best `options.trials` sizes is
+/// solved, and the plaintext that scores best wins, shorter keys
+/// winning ties.
+pub fn break_repeating_key_xor_with_options<S: Scorer + ?Sized>(
+    cipherbytes: Vec<u8>,
+    scorer: &S,
+    options: &KeySizeOptions,
+) -> Result<Solution> {
+    let sizes = estimate_key_sizes(&cipherbytes, options)?;
+    let trials: Vec<(f32, Solution)> = sizes
+        .iter()
+        .take(options.trials.max(1))
+        .map(|&(key_size, _)| {
+            let mut solution = Blocks::new(cipherbytes.clone(), key_size)?.solve_with(scorer);
+            reduce_key(&mut solution);
+            Ok((scorer.score(&solution.plaintext), solution))
+        })
+        .collect::<Result<_>>()?;
+    best_trial(trials, Solution::key_size)
+        .ok_or_else(|| Error::AttackFailed("No key size to try".to_string()))
+}
+
+/// The solution whose plaintext scores best, shorter keys winning ties.
+/// `key_len` gives the length of the key of a solution.
+pub(crate) fn best_trial<T, F: Fn(&T) -> usize>(
+    mut trials: Vec<(f32, T)>,
+    key_len: F,
+) -> Option<T> {
+    let best_score = trials
+        .iter()
+        .map(|(score, _)| *score)
+        .fold(f32::MAX, f32::min);
+    let threshold = best_score + SIZE_TOLERANCE * best_score.abs();
+    trials.retain(|(score, _)| *score <= threshold);
+    trials.sort_by_key(|(_, solution)| key_len(solution));
+    trials.into_iter().next().map(|(_, solution)| solution)
+}
+
+#[test]
+fn test_hamming() {
+    let d = hamming("this is a test".as_bytes(), "wokka wokka!!!".as_bytes()).unwrap();
+    assert_eq!(d, 37_u64);
+    assert!(hamming(b"abc", b"ab").is_err());
+}
+
+#[test]
+fn test_break_repeating_key_xor() {
+    let ciphertext = crate::encoding::read_base64_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/set1/chal6/data/ciphertext.txt"
+    ))
+    .unwrap();
+    let solution = break_repeating_key_xor(ciphertext.clone()).unwrap();
+    assert_eq!(solution.key, b"Terminator X: Bring the noise");
+    assert_eq!(solution.key_size(), 29);
+    assert_eq!(solution.confidence.len(), 29);
+    assert!(solution.confidence.iter().all(|c| (0.0..=1.0).contains(c)));
+    assert!(solution
+        .plaintext
+        .starts_with(b"I'm back and I'm ringin' the bell"));
+    assert_eq!(solution.decrypt(&ciphertext).unwrap(), solution.plaintext);
+}
+
+#[test]
+fn test_estimate_key_sizes() {
+    let ciphertext = crate::encoding::read_base64_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/set1/chal6/data/ciphertext.txt"
+    ))
+    .unwrap();
+    let mut options = KeySizeOptions::default();
+    for method in [KeySizeMethod::Hamming, KeySizeMethod::IndexOfCoincidence].iter() {
+        options.method = *method;
+        let sizes = estimate_key_sizes(&ciphertext, &options).unwrap();
+        assert_eq!(sizes.len(), 39);
+        assert!(sizes[..options.trials].iter().any(|&(size, _)| size == 29));
+        let solution = break_repeating_key_xor_with_options(
+            ciphertext.clone(),
+            &LogLikelihood::default(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(solution.key, b"Terminator X: Bring the noise");
+    }
+
+    options.min_size = 50;
+    options.max_size = 60;
+    assert!(estimate_key_sizes(&ciphertext[..99], &options).is_err());
+    assert_eq!(
+        estimate_key_sizes(&ciphertext[..100], &options)
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(average_distance(b"short", 3).is_err());
+    assert!(index_of_coincidence(b"short", 0).is_err());
+}
+
+#[test]
+fn test_multiple_of_key_size() {
+    let plaintext = b"It was the best of times, it was the worst of times, it was the age \
+        of wisdom, it was the age of foolishness, it was the epoch of belief, it \
+        was the epoch of incredulity, it was the season of Light, it was the \
+        season of Darkness.";
+    let ciphertext = repeating_key_xor(plaintext, b"dickens").unwrap();
+    let options = KeySizeOptions {
+        min_size: 14,
+        max_size: 21,
+        trials: 8,
+        ..KeySizeOptions::default()
+    };
+    let solution =
+        break_repeating_key_xor_with_options(ciphertext, &LogLikelihood::default(), &options)
+            .unwrap();
+    assert_eq!(solution.key, b"dickens");
+    assert_eq!(solution.plaintext, &plaintext[..]);
+}
+
+#[test]
+fn test_solve_with_ngram_model() {
+    use crate::ngram::NgramModel;
+    let mut corpus = String::new();
+    let mut plaintext = String::new();
+    for i in 0..300 {
+        let line = format!(
+            "{{\"id\": {}, \"level\": \"{}\", \"msg\": \"request {} done\"}}\n",
+            i,
+            ["info", "warn", "debug"][i % 3],
+            i * 7
+        );
+        if i % 20 == 0 {
+            plaintext.push_str(&line);
+        } else {
+            corpus.push_str(&line);
+        }
+    }
+    let model = NgramModel::train(corpus.as_bytes(), 3).unwrap();
+    let ciphertext = repeating_key_xor(plaintext.as_bytes(), b"JSON key").unwrap();
+    let blocks = Blocks::new(ciphertext, 8).unwrap();
+    assert_eq!(blocks.solve_with(&model).plaintext, plaintext.as_bytes());
+}
+
+#[test]
+fn test_solve_non_utf8_plaintext() {
+    let plaintext: &[u8] = b"The caf\xe9 on the corner serves cr\xe8me br\xfbl\xe9e every \
+        afternoon, and the regulars say it is the best in town. Nobody knows \
+        where the owner learned the recipe, but the queue outside the door \
+        grows longer every week.";
+    let ciphertext = repeating_key_xor(plaintext, b"K\xffy").unwrap();
+    let solution = Blocks::new(ciphertext, 3).unwrap().solve();
+    assert!(matches!(
+        Blocks::new(plaintext.to_vec(), 0),
+        Err(Error::BadLength(_))
+    ));
+    assert_eq!(solution.plaintext, plaintext);
+    assert_eq!(solution.key, b"K\xffy");
+}
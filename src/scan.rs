@@ -0,0 +1,227 @@
+//! Bulk scanning of single-byte XOR ciphertexts.
+//!
+//! Lines of hex encoded ciphertext are read in batches and broken on a
+//! pool of worker threads with `brute_force_with`. Each worker keeps its
+//! own best lines, merged once the input is exhausted, so memory use
+//! depends on the number of results and not on the size of the input.
+use crate::encoding::hex_decode;
+use crate::error::Result;
+use crate::scoring::{brute_force_with, Candidate, Scorer};
+use std::io::BufRead;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::thread;
+
+/// Number of lines handed to a worker at a time
+const BATCH_SIZE: usize = 1024;
+
+/// Lines handed to a worker, along with their line numbers
+type Batch = Vec<(usize, Vec<u8>)>;
+
+/// Settings of a scan
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Number of lines kept in the results
+    pub top: usize,
+    /// Number of worker threads, 0 for one per available core
+    pub threads: usize,
+}
+
+impl Default for ScanOptions {
+    fn default() -> ScanOptions {
+        ScanOptions { top: 1, threads: 0 }
+    }
+}
+
+/// A line of the input along with its best candidate plaintext
+#[derive(Debug, Clone)]
+pub struct ScanResult {
+    /// Line number, starting at 1
+    pub line: usize,
+    pub ciphertext: Vec<u8>,
+    pub candidate: Candidate,
+}
+
+/// Outcome of a scan
+#[derive(Debug, Clone, Default)]
+pub struct ScanSummary {
+    /// The best lines, best first
+    pub results: Vec<ScanResult>,
+    /// Number of lines read
+    pub lines: usize,
+    /// Number of lines that are not valid hex
+    pub skipped: usize,
+}
+
+/// Break every line of the input as single-byte XOR and keep the best
+/// `options.top` lines. Only the first comma separated field of a line
+/// is read, so CSV files can be scanned as well. Blank lines are ignored
+/// and lines that are not valid hex are counted as skipped.
+/// `progress` is called with the number of lines read so far.
+pub fn scan_lines<R, S, F>(
+    reader: R,
+    scorer: &S,
+    options: &ScanOptions,
+    mut progress: F,
+) -> Result<ScanSummary>
+where
+    R: BufRead,
+    S: Scorer + Sync + ?Sized,
+    F: FnMut(usize),
+{
+    let threads = match options.threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    let (sender, receiver) = mpsc::sync_channel(threads * 2);
+    let receiver = Mutex::new(receiver);
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| scope.spawn(|| scan_batches(&receiver, scorer, options.top)))
+            .collect();
+
+        let mut summary = ScanSummary::default();
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        let mut read_error = None;
+        for line in reader.split(b'\n') {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    read_error = Some(e);
+                    break;
+                }
+            };
+            summary.lines += 1;
+            let field = line.split(|&b| b == b',').next().unwrap_or(&[]);
+            if field.trim_ascii().is_empty() {
+                continue;
+            }
+            batch.push((summary.lines, field.to_vec()));
+            if batch.len() == BATCH_SIZE {
+                let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+                if sender.send(full).is_err() {
+                    break;
+                }
+                progress(summary.lines);
+            }
+        }
+        if !batch.is_empty() {
+            sender.send(batch).ok();
+        }
+        drop(sender);
+        progress(summary.lines);
+
+        for worker in workers {
+            let (results, skipped) = worker.join().expect("scan worker panicked");
+            summary.results.extend(results);
+            summary.skipped += skipped;
+        }
+        summary.results.sort_by(|a, b| {
+            (a.candidate.score, a.line)
+                .partial_cmp(&(b.candidate.score, b.line))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        summary.results.truncate(options.top);
+        match read_error {
+            Some(e) => Err(e.into()),
+            None => Ok(summary),
+        }
+    })
+}
+
+/// Worker loop, breaking batches of lines until the channel is closed.
+/// Returns the best lines of the worker and its number of invalid lines.
+fn scan_batches<S: Scorer + ?Sized>(
+    receiver: &Mutex<Receiver<Batch>>,
+    scorer: &S,
+    top: usize,
+) -> (Vec<ScanResult>, usize) {
+    let mut results = Vec::new();
+    let mut skipped = 0;
+    loop {
+        let batch = receiver.lock().expect("scan worker panicked").recv();
+        let batch = match batch {
+            Ok(batch) => batch,
+            Err(_) => return (results, skipped),
+        };
+        for (line, hex) in batch {
+            match hex_decode(&hex) {
+                Ok(ciphertext) => {
+                    let candidate = brute_force_with(&ciphertext, scorer);
+                    let result = ScanResult {
+                        line,
+                        ciphertext,
+                        candidate,
+                    };
+                    insert_result(&mut results, result, top);
+                }
+                Err(_) => skipped += 1,
+            }
+        }
+    }
+}
+
+/// Insert a result into a list sorted best first, keeping at most `top`
+fn insert_result(results: &mut Vec<ScanResult>, result: ScanResult, top: usize) {
+    let rank = (result.candidate.score, result.line);
+    let position = results.partition_point(|r| (r.candidate.score, r.line) < rank);
+    if position < top {
+        results.insert(position, result);
+        results.truncate(top);
+    }
+}
+
+#[test]
+fn test_scan_lines() {
+    use crate::scoring::LogLikelihood;
+    let data = std::fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/set1/chal4/data/hex_vals.csv"
+    ))
+    .unwrap();
+    let scorer = LogLikelihood::default();
+    let mut reported = 0;
+    let options = ScanOptions { top: 3, threads: 4 };
+    let summary = scan_lines(&data[..], &scorer, &options, |n| reported = n).unwrap();
+    assert_eq!(summary.lines, 327);
+    assert_eq!(reported, summary.lines);
+    assert_eq!(summary.skipped, 0);
+    assert_eq!(summary.results.len(), 3);
+    assert_eq!(summary.results[0].line, 171);
+    assert_eq!(summary.results[0].candidate.key, 0x35);
+    assert_eq!(
+        summary.results[0].candidate.plaintext,
+        b"Now that the party is jumping\n"
+    );
+
+    let single = ScanOptions { top: 3, threads: 1 };
+    let expected: Vec<usize> = summary.results.iter().map(|r| r.line).collect();
+    let lines: Vec<usize> = scan_lines(&data[..], &scorer, &single, |_| {})
+        .unwrap()
+        .results
+        .iter()
+        .map(|r| r.line)
+        .collect();
+    assert_eq!(lines, expected);
+}
+
+#[test]
+fn test_scan_lines_skips_invalid() {
+    use crate::scoring::LogLikelihood;
+    let plaintext = b"Cooking MC's like a pound of bacon";
+    let ciphertext = crate::encoding::hex_encode(crate::xor::single_byte_xor(plaintext, 0x58));
+    let input = format!("zz\n\n{},label\r\n00ff00ff\n", ciphertext);
+    let options = ScanOptions { top: 5, threads: 2 };
+    let summary = scan_lines(
+        input.as_bytes(),
+        &LogLikelihood::default(),
+        &options,
+        |_| {},
+    )
+    .unwrap();
+    assert_eq!(summary.lines, 4);
+    assert_eq!(summary.skipped, 1);
+    assert_eq!(summary.results.len(), 2);
+    assert_eq!(summary.results[0].line, 3);
+    assert_eq!(summary.results[0].candidate.plaintext, plaintext);
+}
@@ -0,0 +1,374 @@
+//! Scoring of candidate plaintexts against English.
+//!
+//! Scores are computed over the full byte histogram of a text, compared
+//! with a reference table of English byte frequencies, or of another
+//! language from `profiles`. Lower scores are
+//! better. Control bytes are penalised on top of their low frequency,
+//! so binary garbage never beats printable text.
+use crate::xor::single_byte_xor;
+use std::borrow::Cow;
+
+/// Frequencies of the letters a to z in English text, in percent
+const ENGLISH_LETTERS: [f32; 26] = [
+    8.17, 1.29, 2.78, 4.25, 12.70, 2.23, 2.02, 6.09, 6.97, 0.15, 0.77, 4.03, 2.41, 6.75, 7.51,
+    1.93, 0.10, 5.99, 6.33, 9.06, 2.76, 0.98, 2.36, 0.15, 1.97, 0.07,
+];
+
+/// Frequencies of the other common bytes in English text,
+/// in percent of all bytes, also used for the other languages
+const ENGLISH_OTHERS: [(u8, f32); 18] = [
+    (b' ', 17.0),
+    (b'.', 0.65),
+    (b',', 0.61),
+    (b'\n', 0.4),
+    (b'"', 0.27),
+    (b'\'', 0.24),
+    (b'-', 0.15),
+    (b'?', 0.05),
+    (b'!', 0.03),
+    (b';', 0.03),
+    (b':', 0.03),
+    (b'(', 0.02),
+    (b')', 0.02),
+    (b'0', 0.1),
+    (b'1', 0.1),
+    (b'2', 0.05),
+    (b'3', 0.03),
+    (b'5', 0.03),
+];
+
+/// Share of all bytes taken by letters, in percent
+const LETTER_SHARE: f32 = 78.0;
+
+/// Share of the letters that are uppercase
+const UPPERCASE_SHARE: f32 = 0.04;
+
+/// Frequency given to printable bytes missing from the tables, in percent
+const PRINTABLE_FLOOR: f32 = 0.005;
+
+/// Frequency assumed for bytes never seen in the reference,
+/// so their logarithm stays finite
+const MIN_FREQUENCY: f32 = 1e-6;
+
+/// Added to the score, per byte of text, for each control byte
+const CONTROL_PENALTY: f32 = 20.0;
+
+/// Relative frequency of every byte value in some reference text
+#[derive(Debug, Clone)]
+pub struct ByteFrequencies {
+    frequencies: [f32; 256],
+}
+
+impl ByteFrequencies {
+    /// Build a table from counts or weights, which are normalised to sum to 1
+    pub fn new(weights: [f32; 256]) -> ByteFrequencies {
+        let total: f32 = weights.iter().sum();
+        let mut frequencies = [0.0; 256];
+        if total > 0.0 {
+            for (f, w) in frequencies.iter_mut().zip(weights.iter()) {
+                *f = w / total;
+            }
+        }
+        ByteFrequencies { frequencies }
+    }
+
+    /// Reference frequencies of English text
+    pub fn english() -> ByteFrequencies {
+        let letters: Vec<(char, f32)> = ('a'..='z').zip(ENGLISH_LETTERS.iter().copied()).collect();
+        ByteFrequencies::from_letters(&letters)
+    }
+
+    /// Reference frequencies of text in a language, given the relative
+    /// frequencies of its lowercase letters. Letters are counted in their
+    /// UTF-8 encoding, in both cases. Spaces, punctuation and digits
+    /// get the same frequencies as in English.
+    pub fn from_letters(letters: &[(char, f32)]) -> ByteFrequencies {
+        let mut weights = [0.0; 256];
+        weights[0x20..0x7f]
+            .iter_mut()
+            .for_each(|w| *w = PRINTABLE_FLOOR);
+        weights[b'\t' as usize] = PRINTABLE_FLOOR;
+        weights[b'\r' as usize] = PRINTABLE_FLOOR;
+        // UTF-8 bytes of accented Latin letters missing from the table
+        weights[0x80..0xc6]
+            .iter_mut()
+            .for_each(|w| *w = PRINTABLE_FLOOR);
+        weights[0xc0] = 0.0;
+        weights[0xc1] = 0.0;
+        for &(b, f) in ENGLISH_OTHERS.iter() {
+            weights[b as usize] = f;
+        }
+        let letters_total: f32 = letters.iter().map(|(_, f)| f).sum();
+        for &(c, f) in letters {
+            let f = f / letters_total * LETTER_SHARE;
+            let uppercase: String = c.to_uppercase().collect();
+            for (s, f) in [
+                (c.to_string(), f * (1.0 - UPPERCASE_SHARE)),
+                (uppercase, f * UPPERCASE_SHARE),
+            ]
+            .iter()
+            {
+                for &b in s.as_bytes() {
+                    weights[b as usize] += f;
+                }
+            }
+        }
+        ByteFrequencies::new(weights)
+    }
+
+    /// Relative frequency of a byte
+    pub fn frequency(&self, byte: u8) -> f32 {
+        self.frequencies[byte as usize]
+    }
+}
+
+/// Number of occurrences of every byte value in a text
+pub fn byte_histogram(text: &[u8]) -> [usize; 256] {
+    let mut counts = [0; 256];
+    for &b in text {
+        counts[b as usize] += 1;
+    }
+    counts
+}
+
+/// Control bytes, except for the usual whitespace
+fn is_control(byte: u8) -> bool {
+    (byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r')) || byte == 0x7f
+}
+
+/// Penalty for the control bytes of a text
+fn control_penalty(counts: &[usize; 256]) -> f32 {
+    let controls: usize = (0..=255u8)
+        .filter(|&b| is_control(b))
+        .map(|b| counts[b as usize])
+        .sum();
+    controls as f32 * CONTROL_PENALTY
+}
+
+/// Anything that rates how much a text looks like a plaintext.
+/// Lower scores are better.
+pub trait Scorer {
+    fn score(&self, text: &[u8]) -> f32;
+}
+
+/// Pearson's chi-squared statistic between the byte histogram of the
+/// text and the expected frequencies, divided by the length of the text
+/// so that texts of different lengths can be compared
+#[derive(Debug, Clone)]
+pub struct ChiSquared {
+    pub expected: ByteFrequencies,
+}
+
+impl Default for ChiSquared {
+    fn default() -> ChiSquared {
+        ChiSquared {
+            expected: ByteFrequencies::english(),
+        }
+    }
+}
+
+impl Scorer for ChiSquared {
+    fn score(&self, text: &[u8]) -> f32 {
+        if text.is_empty() {
+            return f32::MAX;
+        }
+        let n = text.len() as f32;
+        let counts = byte_histogram(text);
+        let mut score = control_penalty(&counts);
+        for b in 0..=255u8 {
+            let expected = self.expected.frequency(b).max(MIN_FREQUENCY) * n;
+            let observed = counts[b as usize] as f32;
+            score += (observed - expected).powi(2) / expected;
+        }
+        score / n
+    }
+}
+
+/// Negative log-likelihood of the text under the expected frequencies,
+/// averaged per byte
+#[derive(Debug, Clone)]
+pub struct LogLikelihood {
+    pub expected: ByteFrequencies,
+}
+
+impl Default for LogLikelihood {
+    fn default() -> LogLikelihood {
+        LogLikelihood {
+            expected: ByteFrequencies::english(),
+        }
+    }
+}
+
+impl Scorer for LogLikelihood {
+    fn score(&self, text: &[u8]) -> f32 {
+        log_likelihood(text, &self.expected)
+    }
+}
+
+/// Negative log-likelihood of the text under the expected frequencies,
+/// averaged per byte, with control bytes penalised
+pub fn log_likelihood(text: &[u8], expected: &ByteFrequencies) -> f32 {
+    if text.is_empty() {
+        return f32::MAX;
+    }
+    let counts = byte_histogram(text);
+    let mut score = control_penalty(&counts);
+    for b in 0..=255u8 {
+        let count = counts[b as usize];
+        if count > 0 {
+            score -= count as f32 * expected.frequency(b).max(MIN_FREQUENCY).ln();
+        }
+    }
+    score / text.len() as f32
+}
+
+/// A candidate plaintext along with the key byte
+/// that produced it and its score.
+/// Lower scores are better.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub key: u8,
+    pub plaintext: Vec<u8>,
+    pub score: f32,
+}
+
+impl Candidate {
+    /// Unscored candidate, worse than any scored one
+    pub fn new(plaintext: Vec<u8>) -> Candidate {
+        Candidate {
+            key: 0,
+            plaintext,
+            score: f32::MAX,
+        }
+    }
+    pub fn get_score<S: Scorer + ?Sized>(&mut self, scorer: &S) {
+        self.score = scorer.score(&self.plaintext);
+    }
+
+    /// The plaintext as text, with invalid UTF-8 replaced by U+FFFD
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.plaintext)
+    }
+}
+
+/// XOR a buffer against a single byte and score the result
+pub fn byte_xor<S: Scorer + ?Sized>(buffer: &[u8], byte: u8, scorer: &S) -> Candidate {
+    let mut candidate = Candidate::new(single_byte_xor(buffer, byte));
+    candidate.key = byte;
+    candidate.get_score(scorer);
+    candidate
+}
+
+/// Find the best candidate plaintext for a buffer
+/// XORed against a single byte, scored against English
+pub fn brute_force(cipherbytes: &[u8]) -> Candidate {
+    brute_force_with(cipherbytes, &LogLikelihood::default())
+}
+
+/// Find the best candidate plaintext for a buffer
+/// XORed against a single byte, with the given scorer
+pub fn brute_force_with<S: Scorer + ?Sized>(cipherbytes: &[u8], scorer: &S) -> Candidate {
+    rank_candidates(cipherbytes, scorer, 1)
+        .pop()
+        .unwrap_or_else(|| Candidate::new(Vec::new()))
+}
+
+/// Try every key byte on a buffer XORed against a single byte and
+/// return the `n` best candidates, best first.
+/// An empty buffer has no candidates.
+pub fn rank_candidates<S: Scorer + ?Sized>(
+    cipherbytes: &[u8],
+    scorer: &S,
+    n: usize,
+) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = (0..=255)
+        .map(|b| byte_xor(cipherbytes, b, scorer))
+        .filter(|candidate| candidate.score < f32::MAX)
+        .collect();
+    candidates.sort_by(|a, b| {
+        a.score
+            .partial_cmp(&b.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    candidates.truncate(n);
+    candidates
+}
+
+#[test]
+fn test_brute_force() {
+    let ciphertext = crate::encoding::hex_decode(
+        "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736",
+    )
+    .unwrap();
+    assert_eq!(
+        brute_force(&ciphertext).text(),
+        "Cooking MC's like a pound of bacon"
+    );
+}
+
+#[test]
+fn test_rank_candidates() {
+    let ciphertext = single_byte_xor(b"Cooking MC's like a pound of bacon", 0x58);
+    let ranked = rank_candidates(&ciphertext, &LogLikelihood::default(), 5);
+    assert_eq!(ranked.len(), 5);
+    assert_eq!(ranked[0].key, 0x58);
+    assert_eq!(ranked[0].text(), "Cooking MC's like a pound of bacon");
+    assert!(ranked.windows(2).all(|w| w[0].score <= w[1].score));
+    assert_eq!(brute_force(&ciphertext).key, 0x58);
+    assert_eq!(
+        rank_candidates(&ciphertext, &LogLikelihood::default(), 256).len(),
+        256
+    );
+    assert!(rank_candidates(b"", &LogLikelihood::default(), 5).is_empty());
+}
+
+#[test]
+fn test_brute_force_latin_1() {
+    // "Voilà, un crème brûlée à la française" in ISO 8859-1
+    let plaintext = b"Voil\xe0, un cr\xe8me br\xfbl\xe9e \xe0 la fran\xe7aise";
+    for key in 0..=255 {
+        let candidate = brute_force(&single_byte_xor(plaintext, key));
+        assert_eq!(candidate.key, key);
+        assert_eq!(candidate.plaintext, &plaintext[..]);
+    }
+    let candidate = brute_force(&single_byte_xor(plaintext, 1));
+    assert!(candidate.text().starts_with("Voil\u{fffd}, un cr"));
+}
+
+#[test]
+fn test_short_mixed_case() {
+    let scorers: [&dyn Scorer; 2] = [&ChiSquared::default(), &LogLikelihood::default()];
+    let plaintexts = [
+        "Hello, World!",
+        "Meet me at 10.",
+        "THE Eagle has LANDED",
+        "Now that the party is jumping\n",
+        "It's a trap!",
+    ];
+    for scorer in scorers.iter() {
+        for plaintext in plaintexts.iter() {
+            for key in 0..=255 {
+                let ciphertext = single_byte_xor(plaintext.as_bytes(), key);
+                assert_eq!(
+                    brute_force_with(&ciphertext, *scorer).plaintext,
+                    plaintext.as_bytes(),
+                    "key {}",
+                    key
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_scorers_rank_english_first() {
+    let english = b"the quick brown fox jumps over the lazy dog";
+    let gibberish = b"qzx jvkw qqzx vkjq zzxq wvqj kzzx qjvw xkq";
+    let binary = single_byte_xor(english, 0x5a);
+    let scorers: [&dyn Scorer; 2] = [&ChiSquared::default(), &LogLikelihood::default()];
+    for scorer in scorers.iter() {
+        assert!(scorer.score(english) < scorer.score(gibberish));
+        assert!(scorer.score(gibberish) < scorer.score(&binary));
+        assert_eq!(scorer.score(b""), f32::MAX);
+    }
+}
@@ -0,0 +1,341 @@
+//! Streaming ECB and CBC encryption over `std::io` readers and writers.
+//!
+//! Data is processed one block at a time, so memory use does not
+//! depend on the size of the message. Messages are padded with
+//! the chosen padding scheme.
+use crate::cipher::BlockCipher;
+use crate::error::{Error, Result};
+use crate::padding::Padding;
+use rand::Rng;
+use std::io::{self, Read, Write};
+
+/// Size of the chunks read from the underlying reader
+const CHUNK_SIZE: usize = 8192;
+
+/// Block cipher mode used by the streaming adapters
+#[derive(Debug, Clone)]
+pub enum BlockMode {
+    Ecb,
+    Cbc { iv: Vec<u8> },
+}
+
+/// Chaining state shared by the encryptor and the decryptor
+struct Chain<C: BlockCipher> {
+    cipher: C,
+    prev_block: Option<Vec<u8>>,
+}
+
+impl<C: BlockCipher> Chain<C> {
+    fn new(cipher: C, mode: BlockMode) -> Result<Chain<C>> {
+        let prev_block = match mode {
+            BlockMode::Ecb => None,
+            BlockMode::Cbc { iv } => {
+                if iv.len() != cipher.block_size() {
+                    return Err(Error::BadLength(format!(
+                        "IV length {} does not match the blocksize {}",
+                        iv.len(),
+                        cipher.block_size()
+                    )));
+                }
+                Some(iv)
+            }
+        };
+        Ok(Chain { cipher, prev_block })
+    }
+
+    fn block_size(&self) -> usize {
+        self.cipher.block_size()
+    }
+
+    fn encrypt_block(&mut self, block: &mut [u8]) {
+        if let Some(prev_block) = &self.prev_block {
+            block.iter_mut().zip(prev_block).for_each(|(b, p)| *b ^= p);
+        }
+        self.cipher.encrypt_block(block);
+        if let Some(prev_block) = &mut self.prev_block {
+            prev_block.copy_from_slice(block);
+        }
+    }
+
+    fn decrypt_block(&mut self, block: &mut [u8]) {
+        let ciphertext = block.to_vec();
+        self.cipher.decrypt_block(block);
+        if let Some(prev_block) = &mut self.prev_block {
+            block
+                .iter_mut()
+                .zip(prev_block.iter())
+                .for_each(|(b, p)| *b ^= p);
+            *prev_block = ciphertext;
+        }
+    }
+}
+
+/// Encrypts everything written to it and writes the
+/// ciphertext to the wrapped writer.
+/// `finish` must be called to write the final padded block.
+pub struct Encryptor<W: Write, C: BlockCipher> {
+    inner: W,
+    chain: Chain<C>,
+    padding: Padding,
+    pending: Vec<u8>,
+}
+
+impl<W: Write, C: BlockCipher> Encryptor<W, C> {
+    pub fn new(inner: W, cipher: C, mode: BlockMode, padding: Padding) -> Result<Encryptor<W, C>> {
+        Ok(Encryptor {
+            inner,
+            chain: Chain::new(cipher, mode)?,
+            padding,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Pad and write the final block, returning the wrapped writer.
+    /// The random bytes of ISO 10126 padding are drawn from `rng`.
+    pub fn finish<R: Rng + ?Sized>(mut self, rng: &mut R) -> Result<W> {
+        let block_size = self.chain.block_size();
+        let mut last_blocks = self.padding.pad_with_rng(&self.pending, block_size, rng)?;
+        for block in last_blocks.chunks_exact_mut(block_size) {
+            self.chain.encrypt_block(block);
+        }
+        self.inner.write_all(&last_blocks)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write, C: BlockCipher> Write for Encryptor<W, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let block_size = self.chain.block_size();
+        let full_len = self.pending.len() / block_size * block_size;
+        for block in self.pending[..full_len].chunks_exact_mut(block_size) {
+            self.chain.encrypt_block(block);
+        }
+        self.inner.write_all(&self.pending[..full_len])?;
+        self.pending.drain(..full_len);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts the ciphertext read from the wrapped reader.
+/// The final block is held back until the end of the input
+/// so its padding can be checked and removed.
+pub struct Decryptor<R: Read, C: BlockCipher> {
+    inner: R,
+    chain: Chain<C>,
+    padding: Padding,
+    pending: Vec<u8>,
+    output: Vec<u8>,
+    position: usize,
+    /// Decrypted final block, still padded, once the input has ended
+    last_block: Option<Vec<u8>>,
+    done: bool,
+}
+
+impl<R: Read, C: BlockCipher> Decryptor<R, C> {
+    pub fn new(inner: R, cipher: C, mode: BlockMode, padding: Padding) -> Result<Decryptor<R, C>> {
+        Ok(Decryptor {
+            inner,
+            chain: Chain::new(cipher, mode)?,
+            padding,
+            pending: Vec::new(),
+            output: Vec::new(),
+            position: 0,
+            last_block: None,
+            done: false,
+        })
+    }
+
+    /// Read the next chunk of ciphertext and decrypt
+    /// every block except the last one seen so far.
+    /// Errors at the end of the input are returned again by later calls.
+    fn fill_output(&mut self) -> Result<()> {
+        let block_size = self.chain.block_size();
+        self.output.clear();
+        self.position = 0;
+        if let Some(last_block) = &self.last_block {
+            self.output = self.padding.unpad(last_block, block_size)?;
+            self.done = true;
+            return Ok(());
+        }
+        let mut chunk = [0; CHUNK_SIZE];
+        let n = loop {
+            match self.inner.read(&mut chunk) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        if n == 0 {
+            if !self.pending.len().is_multiple_of(block_size) {
+                return Err(Error::BadLength(
+                    "Ciphertext length must be a multiple of the blocksize".to_string(),
+                ));
+            }
+            let mut last_block = std::mem::take(&mut self.pending);
+            if !last_block.is_empty() {
+                self.chain.decrypt_block(&mut last_block);
+            }
+            self.last_block = Some(last_block);
+            return self.fill_output();
+        }
+        self.pending.extend_from_slice(&chunk[..n]);
+        if self.pending.len() > block_size {
+            let ready_len = (self.pending.len() - 1) / block_size * block_size;
+            let mut ready: Vec<u8> = self.pending.drain(..ready_len).collect();
+            for block in ready.chunks_exact_mut(block_size) {
+                self.chain.decrypt_block(block);
+            }
+            self.output = ready;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read, C: BlockCipher> Read for Decryptor<R, C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.output.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.fill_output()?;
+        }
+        let n = buf.len().min(self.output.len() - self.position);
+        buf[..n].copy_from_slice(&self.output[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// Reader handing out a single byte per call, to exercise block boundaries
+#[cfg(test)]
+struct ByteReader<'a>(&'a [u8]);
+
+#[cfg(test)]
+impl Read for ByteReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0.is_empty() || buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.0[0];
+        self.0 = &self.0[1..];
+        Ok(1)
+    }
+}
+
+#[test]
+fn test_stream_matches_slice_modes() {
+    use crate::aes::{Aes, BLOCK_SIZE};
+    use crate::modes::{encrypt_cbc, encrypt_ecb};
+    use crate::random::seeded_rng;
+    let cipher = Aes::new(b"YELLOW SUBMARINE").unwrap();
+    let iv = vec![3; BLOCK_SIZE];
+    for len in [0, 1, 15, 16, 17, 100].iter() {
+        let msg: Vec<u8> = (0..*len).map(|i| i as u8).collect();
+        let padded = Padding::Pkcs7.pad(&msg, BLOCK_SIZE).unwrap();
+        let modes = [
+            (BlockMode::Ecb, encrypt_ecb(&cipher, &padded).unwrap()),
+            (
+                BlockMode::Cbc { iv: iv.clone() },
+                encrypt_cbc(&cipher, &padded, &iv).unwrap(),
+            ),
+        ];
+        for (mode, expected) in modes.iter() {
+            let mut encryptor =
+                Encryptor::new(Vec::new(), cipher.clone(), mode.clone(), Padding::Pkcs7).unwrap();
+            for chunk in msg.chunks(7) {
+                encryptor.write_all(chunk).unwrap();
+            }
+            let ciphertext = encryptor.finish(&mut seeded_rng(0)).unwrap();
+            assert_eq!(&ciphertext, expected);
+
+            let mut decryptor = Decryptor::new(
+                ByteReader(&ciphertext),
+                cipher.clone(),
+                mode.clone(),
+                Padding::Pkcs7,
+            )
+            .unwrap();
+            let mut plaintext = Vec::new();
+            decryptor.read_to_end(&mut plaintext).unwrap();
+            assert_eq!(plaintext, msg);
+        }
+    }
+}
+
+#[test]
+fn test_stream_bad_input() {
+    use crate::aes::{Aes, BLOCK_SIZE};
+    let cipher = Aes::new(b"YELLOW SUBMARINE").unwrap();
+    assert!(Encryptor::new(
+        Vec::new(),
+        cipher.clone(),
+        BlockMode::Cbc { iv: vec![0; 3] },
+        Padding::Pkcs7
+    )
+    .is_err());
+
+    let ciphertext = crate::modes::encrypt_ecb(&cipher, &[0; BLOCK_SIZE]).unwrap();
+    let mut plaintext = Vec::new();
+    let mut decryptor = Decryptor::new(
+        &ciphertext[..],
+        cipher.clone(),
+        BlockMode::Ecb,
+        Padding::Pkcs7,
+    )
+    .unwrap();
+    assert!(decryptor.read_to_end(&mut plaintext).is_err());
+    assert!(decryptor.read_to_end(&mut plaintext).is_err());
+
+    let mut decryptor = Decryptor::new(
+        &ciphertext[1..],
+        cipher.clone(),
+        BlockMode::Ecb,
+        Padding::Pkcs7,
+    )
+    .unwrap();
+    assert!(decryptor.read_to_end(&mut plaintext).is_err());
+    assert!(decryptor.read_to_end(&mut plaintext).is_err());
+
+    let mut decryptor = Decryptor::new(&[][..], cipher, BlockMode::Ecb, Padding::Pkcs7).unwrap();
+    assert!(decryptor.read_to_end(&mut plaintext).is_err());
+    // The error is not mistaken for the end of the stream by later reads
+    assert!(decryptor.read_to_end(&mut plaintext).is_err());
+}
+
+#[test]
+fn test_stream_padding_schemes() {
+    use crate::aes::{Aes, BLOCK_SIZE};
+    use crate::modes::encrypt_ecb;
+    use crate::random::seeded_rng;
+    let cipher = Aes::new(b"YELLOW SUBMARINE").unwrap();
+    let schemes = [
+        Padding::None,
+        Padding::Zero,
+        Padding::Iso7816,
+        Padding::Iso10126,
+    ];
+    for scheme in schemes.iter() {
+        for len in [0, 16, 32].iter() {
+            let msg = vec![b'A'; *len];
+            let mut encryptor =
+                Encryptor::new(Vec::new(), cipher.clone(), BlockMode::Ecb, *scheme).unwrap();
+            encryptor.write_all(&msg).unwrap();
+            let ciphertext = encryptor.finish(&mut seeded_rng(1)).unwrap();
+            let padded = scheme
+                .pad_with_rng(&msg, BLOCK_SIZE, &mut seeded_rng(1))
+                .unwrap();
+            assert_eq!(ciphertext, encrypt_ecb(&cipher, &padded).unwrap());
+            let mut decryptor =
+                Decryptor::new(&ciphertext[..], cipher.clone(), BlockMode::Ecb, *scheme).unwrap();
+            let mut plaintext = Vec::new();
+            decryptor.read_to_end(&mut plaintext).unwrap();
+            assert_eq!(plaintext, msg);
+        }
+    }
+}
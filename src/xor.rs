@@ -0,0 +1,171 @@
+use crate::error::{Error, Result};
+use std::io::{self, Read, Write};
+
+/// XOR two byte slices of the same length
+pub fn xor(x: &[u8], y: &[u8]) -> Result<Vec<u8>> {
+    if x.len() != y.len() {
+        return Err(Error::BadLength(format!(
+            "Cannot XOR slices of length {} and {}",
+            x.len(),
+            y.len()
+        )));
+    }
+    Ok(x.iter().zip(y).map(|(a, b)| a ^ b).collect())
+}
+
+/// XOR every byte of a buffer against a single byte
+pub fn single_byte_xor(buffer: &[u8], byte: u8) -> Vec<u8> {
+    buffer.iter().map(|b| b ^ byte).collect()
+}
+
+/// XOR a buffer against a key repeated over its whole length
+pub fn repeating_key_xor(buffer: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    let mut output = buffer.to_vec();
+    RepeatingKeyXor::new(key)?.apply(&mut output);
+    Ok(output)
+}
+
+/// Repeating-key XOR keystream, keeping track of its position in the
+/// key so that a message can be processed one chunk at a time
+#[derive(Debug, Clone)]
+pub struct RepeatingKeyXor {
+    key: Vec<u8>,
+    position: usize,
+}
+
+impl RepeatingKeyXor {
+    pub fn new(key: &[u8]) -> Result<RepeatingKeyXor> {
+        if key.is_empty() {
+            return Err(Error::BadLength("XOR key cannot be empty".to_string()));
+        }
+        Ok(RepeatingKeyXor {
+            key: key.to_vec(),
+            position: 0,
+        })
+    }
+
+    /// XOR the next bytes of the message in place
+    pub fn apply(&mut self, buffer: &mut [u8]) {
+        for b in buffer.iter_mut() {
+            *b ^= self.key[self.position];
+            self.position = (self.position + 1) % self.key.len();
+        }
+    }
+}
+
+/// Reader XORing the bytes of the wrapped reader with a repeating key
+pub struct XorReader<R: Read> {
+    inner: R,
+    keystream: RepeatingKeyXor,
+}
+
+impl<R: Read> XorReader<R> {
+    pub fn new(inner: R, key: &[u8]) -> Result<XorReader<R>> {
+        Ok(XorReader {
+            inner,
+            keystream: RepeatingKeyXor::new(key)?,
+        })
+    }
+}
+
+impl<R: Read> Read for XorReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.keystream.apply(&mut buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Writer XORing everything written to it with a repeating key
+/// before passing it on to the wrapped writer
+pub struct XorWriter<W: Write> {
+    inner: W,
+    keystream: RepeatingKeyXor,
+}
+
+impl<W: Write> XorWriter<W> {
+    pub fn new(inner: W, key: &[u8]) -> Result<XorWriter<W>> {
+        Ok(XorWriter {
+            inner,
+            keystream: RepeatingKeyXor::new(key)?,
+        })
+    }
+
+    /// Return the wrapped writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for XorWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut data = buf.to_vec();
+        self.keystream.apply(&mut data);
+        self.inner.write_all(&data)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[test]
+fn test_xor() {
+    let x = crate::encoding::hex_decode("1c0111001f010100061a024b53535009181c").unwrap();
+    let y = crate::encoding::hex_decode("686974207468652062756c6c277320657965").unwrap();
+    assert_eq!(
+        crate::encoding::hex_encode(xor(&x, &y).unwrap()),
+        "746865206b696420646f6e277420706c6179"
+    );
+    assert!(xor(&x, &y[1..]).is_err());
+}
+
+#[test]
+fn test_single_byte_xor() {
+    assert_eq!(single_byte_xor(b"abc", 0), b"abc");
+    assert_eq!(single_byte_xor(&single_byte_xor(b"abc", 42), 42), b"abc");
+}
+
+#[test]
+fn test_repeating_key_xor() {
+    let plaintext = b"Burning 'em, if you ain't quick and nimble\nI go crazy when I hear a cymbal";
+    assert_eq!(
+        crate::encoding::hex_encode(repeating_key_xor(plaintext, b"ICE").unwrap()),
+        "0b3637272a2b2e63622c2e69692a23693a2a3c6324202d623d63343c2a26226324272765272a282b2f20430a652e2c652a3124333a653e2b2027630c692b20283165286326302e27282f"
+    );
+}
+
+#[test]
+fn test_repeating_key_xor_stream() {
+    let plaintext = b"Burning 'em, if you ain't quick and nimble\nI go crazy when I hear a cymbal";
+    let expected = repeating_key_xor(plaintext, b"ICE").unwrap();
+
+    let mut ciphertext = Vec::new();
+    XorReader::new(&plaintext[..], b"ICE")
+        .unwrap()
+        .read_to_end(&mut ciphertext)
+        .unwrap();
+    assert_eq!(ciphertext, expected);
+
+    for chunk_size in 1..8 {
+        let mut writer = XorWriter::new(Vec::new(), b"ICE").unwrap();
+        for chunk in plaintext.chunks(chunk_size) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(writer.into_inner(), expected);
+    }
+
+    let key: Vec<u8> = (0..=255).collect();
+    let mut keystream = RepeatingKeyXor::new(&key).unwrap();
+    let mut data = vec![0; 1000];
+    for chunk in data.chunks_mut(7) {
+        keystream.apply(chunk);
+    }
+    assert_eq!(data, repeating_key_xor(&[0; 1000], &key).unwrap());
+    assert!(RepeatingKeyXor::new(b"").is_err());
+    assert!(matches!(
+        repeating_key_xor(plaintext, b""),
+        Err(Error::BadLength(_))
+    ));
+}